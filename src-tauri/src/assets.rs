@@ -0,0 +1,273 @@
+//! Content-addressed storage for robot images and models.
+//!
+//! Files live in `<app data>/assets/<robot id>/<sha256>.<ext>`, and the
+//! database stores only the relative part (`<robot id>/<sha256>.<ext>`), the
+//! asset ID. Moving the data directory therefore keeps every robot intact.
+//! A generation job writes into the folder of the robot it will become, so
+//! its id doubles as the robot id.
+
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::preprocess;
+
+pub struct AssetStore {
+    data_dir: PathBuf,
+    root: PathBuf,
+}
+
+impl AssetStore {
+    pub fn open(app_data_dir: &Path) -> AppResult<Self> {
+        let root = app_data_dir.join("assets");
+        fs::create_dir_all(&root)?;
+        Ok(Self {
+            data_dir: app_data_dir.to_path_buf(),
+            root,
+        })
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Where `maintenance` moves orphaned files.
+    pub fn quarantine_dir(&self) -> PathBuf {
+        self.data_dir.join("quarantine")
+    }
+
+    /// Store `bytes` for `robot_id` and return the asset ID. Storing the same
+    /// content twice for one robot yields the same ID and a single file.
+    pub fn put(&self, robot_id: &str, bytes: &[u8], extension: &str) -> AppResult<String> {
+        let id = format!("{}/{}.{}", robot_id, preprocess::content_hash(bytes), extension);
+        let path = self.path_of(&id)?;
+        if path.exists() {
+            return Ok(id);
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write then rename, so a crash never leaves a truncated file under a valid hash.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes)?;
+        fs::rename(&tmp, &path)?;
+        Ok(id)
+    }
+
+    /// Copy an existing file into the store for `robot_id`.
+    pub fn import_file(&self, robot_id: &str, path: &Path) -> AppResult<String> {
+        let bytes = fs::read(path)?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("bin").to_lowercase();
+        self.put(robot_id, &bytes, &extension)
+    }
+
+    /// Absolute location of `id`, without checking that the file exists.
+    fn path_of(&self, id: &str) -> AppResult<PathBuf> {
+        let relative = Path::new(id);
+        let valid = relative.components().count() == 2
+            && relative.components().all(|c| matches!(c, Component::Normal(_)));
+        if !valid {
+            return Err(AppError::invalid_input(format!("Invalid asset ID: {}", id)));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Usable path for an asset ID. Absolute paths written before the asset
+    /// store existed are passed through, so old jobs can still resume, but
+    /// only when they point into the app data directory.
+    pub fn resolve(&self, id: &str) -> AppResult<PathBuf> {
+        let path = if Path::new(id).is_absolute() {
+            let legacy = Path::new(id);
+            let inside = legacy.starts_with(&self.data_dir)
+                && !legacy.components().any(|c| c == Component::ParentDir);
+            if !inside {
+                return Err(AppError::invalid_input(format!("Invalid asset ID: {}", id)));
+            }
+            legacy.to_path_buf()
+        } else {
+            self.path_of(id)?
+        };
+        if !path.is_file() {
+            return Err(AppError::not_found(format!("Asset not found: {}", id)));
+        }
+        Ok(path)
+    }
+
+    pub fn read(&self, id: &str) -> AppResult<Vec<u8>> {
+        Ok(fs::read(self.resolve(id)?)?)
+    }
+
+    /// Delete a legacy absolute-path asset. Store assets are removed with their robot folder.
+    pub fn remove_legacy(&self, path: &str) {
+        if !Path::new(path).is_absolute() {
+            return;
+        }
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("Failed to remove {}: {}", path, e),
+        }
+    }
+
+    /// Delete the assets of `robot_id` other than `keep`.
+    pub fn retain(&self, robot_id: &str, keep: &[String]) {
+        let Ok(files) = fs::read_dir(self.root.join(robot_id)) else {
+            return;
+        };
+        for path in files.flatten().map(|e| e.path()) {
+            let id = format!("{}/{}", robot_id, path.file_name().unwrap_or_default().to_string_lossy());
+            if !keep.contains(&id) {
+                if let Err(e) = fs::remove_file(&path) {
+                    eprintln!("Failed to remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    /// Delete every asset of `robot_id`.
+    pub fn remove_robot(&self, robot_id: &str) {
+        let mut components = Path::new(robot_id).components();
+        if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            return;
+        }
+        match fs::remove_dir_all(self.root.join(robot_id)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("Failed to remove assets of robot {}: {}", robot_id, e),
+        }
+    }
+}
+
+/// Move files of robots saved with absolute paths into the store and point
+/// their rows at the new asset IDs. Files that are already gone are left for
+/// the integrity check to report.
+pub fn migrate_legacy_paths(conn: &Connection, store: &AssetStore) -> AppResult<usize> {
+    let mut migrated = 0;
+    for robot in db::get_robots(conn)? {
+        let fields = [
+            &robot.original_image_path,
+            &robot.image_path,
+            &robot.model_path,
+            &robot.attack_model_path,
+        ];
+        if !fields.iter().any(|p| Path::new(p.as_str()).is_absolute()) {
+            continue;
+        }
+
+        let import = |path: &String| -> String {
+            let legacy = Path::new(path.as_str());
+            if !legacy.is_absolute() || !legacy.is_file() {
+                return path.clone();
+            }
+            match store.import_file(&robot.id, legacy) {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("Failed to move {} into the asset store: {}", path, e);
+                    path.clone()
+                }
+            }
+        };
+        let ids = fields.map(import);
+        // Rows whose legacy files are all gone stay as they are, so they
+        // aren't rewritten and counted again on every startup.
+        if fields.iter().zip(&ids).all(|(legacy, id)| *legacy == id) {
+            continue;
+        }
+        db::set_robot_assets(conn, &robot.id, &ids[0], &ids[1], &ids[2], &ids[3])?;
+
+        for (legacy, id) in fields.iter().zip(&ids) {
+            if *legacy != id {
+                store.remove_legacy(legacy);
+            }
+        }
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::test_support::TestInstall;
+
+    fn legacy_robot(id: &str, dir: &Path) -> db::RobotRecord {
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        db::RobotRecord {
+            id: id.to_string(),
+            name: "Ramen Titan".to_string(),
+            hp: 1000,
+            atk: 50,
+            def: 20,
+            original_image_path: path(&format!("{}_original.png", id)),
+            image_path: path(&format!("{}_concept.png", id)),
+            model_path: path(&format!("{}.glb", id)),
+            attack_model_path: path(&format!("{}_attack.glb", id)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn put_is_content_addressed() {
+        let install = TestInstall::new();
+        let store = &install.store;
+        let a = store.put("robot", b"glb bytes", "glb").unwrap();
+        assert_eq!(store.put("robot", b"glb bytes", "glb").unwrap(), a);
+        assert_eq!(store.read(&a).unwrap(), b"glb bytes");
+        assert!(store.resolve("../outside.glb").is_err());
+        assert!(store.resolve("robot/../../outside.glb").is_err());
+    }
+
+    #[test]
+    fn resolve_accepts_only_legacy_paths_in_the_data_dir() {
+        let install = TestInstall::new();
+        let store = &install.store;
+        let legacy = install.dir.path().join("old.glb");
+        fs::write(&legacy, b"glb bytes").unwrap();
+        assert_eq!(store.resolve(&legacy.to_string_lossy()).unwrap(), legacy);
+
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        let err = store.resolve(&outside.path().to_string_lossy()).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        let escaped = install.dir.path().join("..").join("old.glb");
+        assert!(store.resolve(&escaped.to_string_lossy()).is_err());
+    }
+
+    #[test]
+    fn migrates_legacy_files_once() {
+        let install = TestInstall::new();
+        let (store, conn) = (&install.store, install.conn());
+        let robot = legacy_robot("old", install.dir.path());
+        for path in [&robot.original_image_path, &robot.image_path, &robot.model_path, &robot.attack_model_path] {
+            fs::write(path, path.as_bytes()).unwrap();
+        }
+        db::insert_robot(&conn, &robot).unwrap();
+
+        assert_eq!(migrate_legacy_paths(&conn, store).unwrap(), 1);
+        let migrated = db::get_robot(&conn, "old").unwrap().unwrap();
+        assert!(migrated.model_path.starts_with("old/"));
+        assert_eq!(store.read(&migrated.model_path).unwrap(), robot.model_path.as_bytes());
+        assert!(!Path::new(&robot.model_path).exists());
+
+        assert_eq!(migrate_legacy_paths(&conn, store).unwrap(), 0);
+    }
+
+    #[test]
+    fn leaves_robots_with_missing_legacy_files_alone() {
+        let install = TestInstall::new();
+        let (store, conn) = (&install.store, install.conn());
+        let robot = legacy_robot("gone", install.dir.path());
+        db::insert_robot(&conn, &robot).unwrap();
+
+        assert_eq!(migrate_legacy_paths(&conn, store).unwrap(), 0);
+        assert_eq!(migrate_legacy_paths(&conn, store).unwrap(), 0);
+        let unchanged = db::get_robot(&conn, "gone").unwrap().unwrap();
+        assert_eq!(unchanged.model_path, robot.model_path);
+    }
+}
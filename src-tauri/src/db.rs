@@ -1,7 +1,10 @@
 use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use crate::error::AppResult;
+use crate::stats::{Nutrition, StatAdjustment};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct RobotRecord {
     pub id: String,
     pub name: String,
@@ -9,42 +12,408 @@ pub struct RobotRecord {
     pub hp: i32,
     pub atk: i32,
     pub def: i32,
+    /// Asset IDs (see `assets`); resolve them with the `resolve_asset` command.
     pub original_image_path: String,
     pub image_path: String,
     pub model_path: String,
     pub attack_model_path: String,
     pub created_at: i64,
     pub generation_time_ms: i64,
+    #[serde(default)]
+    pub is_favorite: bool,
+    /// Set when any generated stat had to be defaulted or clamped.
+    #[serde(default)]
+    pub stats_adjusted: bool,
+    #[serde(default)]
+    pub stat_adjustments: Vec<StatAdjustment>,
+    #[serde(default)]
+    pub nutrition: Nutrition,
+    /// `None` for robots whose stats were chosen by the model.
+    #[serde(default)]
+    pub stat_formula_version: Option<u32>,
+    /// `preprocess::perceptual_hash` of the source photo.
+    #[serde(default)]
+    pub perceptual_hash: Option<String>,
+}
+
+/// Editable fields of a robot; `None` leaves the column unchanged.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RobotUpdate {
+    pub name: Option<String>,
+    pub lore: Option<String>,
+    pub is_favorite: Option<bool>,
 }
 
-pub fn init_db(db_path: &std::path::Path) -> Result<Connection> {
-    let conn = Connection::open(db_path)?;
+/// Ordered schema migrations. Entry `i` upgrades the database from
+/// `user_version = i` to `user_version = i + 1`. Never edit a shipped entry;
+/// append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial robots table (matches installs created before versioning)
+    "CREATE TABLE IF NOT EXISTS robots (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        lore TEXT NOT NULL,
+        hp INTEGER NOT NULL,
+        atk INTEGER NOT NULL,
+        def INTEGER NOT NULL,
+        original_image_path TEXT NOT NULL,
+        image_path TEXT NOT NULL,
+        model_path TEXT NOT NULL,
+        attack_model_path TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        generation_time_ms INTEGER NOT NULL
+    );",
+    // 2: favorites
+    "ALTER TABLE robots ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0;",
+    // 3: indexes backing the sort keys of `query_robots`
+    "CREATE INDEX IF NOT EXISTS idx_robots_created_at ON robots (created_at);
+     CREATE INDEX IF NOT EXISTS idx_robots_hp ON robots (hp);
+     CREATE INDEX IF NOT EXISTS idx_robots_atk ON robots (atk);
+     CREATE INDEX IF NOT EXISTS idx_robots_def ON robots (def);
+     CREATE INDEX IF NOT EXISTS idx_robots_generation_time_ms ON robots (generation_time_ms);",
+    // 4: full-text index over name/lore. Keyed by `id` rather than using external
+    // content, because the implicit rowid of a TEXT-keyed table may change on VACUUM.
+    // The trigram tokenizer gives substring matching, which also works for Japanese.
+    "CREATE VIRTUAL TABLE IF NOT EXISTS robots_fts USING fts5(id UNINDEXED, name, lore, tokenize = 'trigram');
+     INSERT INTO robots_fts (id, name, lore) SELECT id, name, lore FROM robots;
+     CREATE TRIGGER IF NOT EXISTS robots_fts_insert AFTER INSERT ON robots BEGIN
+         INSERT INTO robots_fts (id, name, lore) VALUES (new.id, new.name, new.lore);
+     END;
+     CREATE TRIGGER IF NOT EXISTS robots_fts_delete AFTER DELETE ON robots BEGIN
+         DELETE FROM robots_fts WHERE id = old.id;
+     END;
+     CREATE TRIGGER IF NOT EXISTS robots_fts_update AFTER UPDATE OF id, name, lore ON robots BEGIN
+         UPDATE robots_fts SET id = new.id, name = new.name, lore = new.lore WHERE id = old.id;
+     END;",
+    // 5: persisted generation pipeline state, so paid Meshy tasks survive restarts
+    "CREATE TABLE IF NOT EXISTS generation_jobs (
+        id TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        original_image_path TEXT NOT NULL,
+        stats_json TEXT,
+        generated_image_path TEXT,
+        image_task_id TEXT,
+        rig_task_id TEXT,
+        idle_anim_task_id TEXT,
+        attack_anim_task_id TEXT,
+        rigging_error TEXT,
+        robot_id TEXT,
+        error TEXT,
+        created_at_ms INTEGER NOT NULL,
+        updated_at_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_generation_jobs_status ON generation_jobs (status);",
+    // 6: flag robots whose generated stats were defaulted or clamped
+    "ALTER TABLE robots ADD COLUMN stats_adjusted INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE robots ADD COLUMN stat_adjustments TEXT NOT NULL DEFAULT '[]';",
+    // 7: nutrition estimate the stats were derived from
+    "ALTER TABLE robots ADD COLUMN food_name TEXT;
+     ALTER TABLE robots ADD COLUMN calories REAL;
+     ALTER TABLE robots ADD COLUMN protein_g REAL;
+     ALTER TABLE robots ADD COLUMN fiber_g REAL;",
+    // 8: which stats::FORMULA_VERSION computed the stats (NULL: chosen by the model)
+    "ALTER TABLE robots ADD COLUMN stat_formula_version INTEGER;",
+    // 9: SHA-256 of the preprocessed upload
+    "ALTER TABLE generation_jobs ADD COLUMN image_hash TEXT;",
+    // 10: perceptual hash of the upload, for duplicate photo detection
+    "ALTER TABLE generation_jobs ADD COLUMN perceptual_hash TEXT;
+     ALTER TABLE robots ADD COLUMN perceptual_hash TEXT;",
+];
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS robots (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            lore TEXT NOT NULL,
-            hp INTEGER NOT NULL,
-            atk INTEGER NOT NULL,
-            def INTEGER NOT NULL,
-            original_image_path TEXT NOT NULL,
-            image_path TEXT NOT NULL,
-            model_path TEXT NOT NULL,
-            attack_model_path TEXT NOT NULL,
-            created_at INTEGER NOT NULL,
-            generation_time_ms INTEGER NOT NULL
-        )",
-        [],
-    )?;
+/// Schema version this build of the app expects.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MigrationError {
+    /// Version the database was at when the upgrade started.
+    pub from_version: u32,
+    /// Migration step that failed.
+    pub failed_version: u32,
+    pub message: String,
+}
+
+/// Result of opening the database, reported to the UI via `get_db_status`.
+#[derive(Serialize, Debug, Clone)]
+pub struct DbStatus {
+    pub schema_version: u32,
+    pub target_version: u32,
+    pub migration_error: Option<MigrationError>,
+}
+
+pub fn init_db(db_path: &std::path::Path) -> AppResult<(Connection, DbStatus)> {
+    let mut conn = Connection::open(db_path)?;
+
+    let migration_error = migrate(&mut conn).err();
+    let status = DbStatus {
+        schema_version: schema_version(&conn)?,
+        target_version: SCHEMA_VERSION,
+        migration_error,
+    };
+
+    Ok((conn, status))
+}
+
+pub fn schema_version(conn: &Connection) -> AppResult<u32> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Apply all pending migrations. Each step runs in its own transaction together
+/// with its `user_version` bump, so a failure leaves the database at the last
+/// successfully applied version.
+pub fn migrate(conn: &mut Connection) -> std::result::Result<u32, MigrationError> {
+    let from_version = schema_version(conn).map_err(|e| MigrationError {
+        from_version: 0,
+        failed_version: 0,
+        message: format!("Failed to read schema version: {}", e),
+    })?;
+
+    if from_version > SCHEMA_VERSION {
+        return Err(MigrationError {
+            from_version,
+            failed_version: from_version,
+            message: format!(
+                "Database schema version {} is newer than this app supports ({})",
+                from_version, SCHEMA_VERSION
+            ),
+        });
+    }
+
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(from_version as usize) {
+        let version = index as u32 + 1;
+        let apply = |conn: &mut Connection| -> Result<()> {
+            let tx = conn.transaction()?;
+            tx.execute_batch(sql)?;
+            tx.pragma_update(None, "user_version", version)?;
+            tx.commit()
+        };
+
+        apply(conn).map_err(|e| MigrationError {
+            from_version,
+            failed_version: version,
+            message: e.to_string(),
+        })?;
+    }
+
+    Ok(SCHEMA_VERSION)
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RobotSortKey {
+    #[default]
+    CreatedAt,
+    Hp,
+    Atk,
+    Def,
+    GenerationTimeMs,
+}
+
+impl RobotSortKey {
+    fn column(self) -> &'static str {
+        match self {
+            RobotSortKey::CreatedAt => "created_at",
+            RobotSortKey::Hp => "hp",
+            RobotSortKey::Atk => "atk",
+            RobotSortKey::Def => "def",
+            RobotSortKey::GenerationTimeMs => "generation_time_ms",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortDirection {
+    fn keyword(self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+}
+
+/// Inclusive stat range; either bound may be omitted.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct StatRange {
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const MAX_PAGE_SIZE: u32 = 500;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RobotQuery {
+    pub sort_by: RobotSortKey,
+    pub direction: SortDirection,
+    /// Page size, defaults to `DEFAULT_PAGE_SIZE` and is capped at `MAX_PAGE_SIZE`.
+    pub limit: Option<u32>,
+    pub offset: u32,
+    pub hp: StatRange,
+    pub atk: StatRange,
+    pub def: StatRange,
+    pub favorites_only: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RobotPage {
+    pub robots: Vec<RobotRecord>,
+    /// Number of robots matching the filters, ignoring paging.
+    pub total: i64,
+    /// Offset of the next page, or `None` when this is the last one.
+    pub next_offset: Option<u32>,
+}
 
-    Ok(conn)
+/// Markers wrapped around matched text in search snippets. Everything else in a
+/// snippet is raw robot text, so the UI must escape it before rendering.
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RobotSearchHit {
+    pub robot: RobotRecord,
+    pub name_snippet: String,
+    pub lore_snippet: String,
+    /// bm25 score from FTS5; lower is a better match. Always 0 for short queries.
+    pub rank: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
 }
 
-pub fn insert_robot(conn: &Connection, robot: &RobotRecord) -> Result<()> {
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queued" => Some(JobStatus::Queued),
+            "running" => Some(JobStatus::Running),
+            "completed" => Some(JobStatus::Completed),
+            "failed" => Some(JobStatus::Failed),
+            "cancelled" => Some(JobStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of one `run_generation_pipeline` invocation. Each `Option` field is
+/// filled in as soon as its step finishes, so an interrupted job can resume from
+/// the first missing one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenerationJob {
+    pub id: String,
+    pub status: JobStatus,
+    /// Asset ID, or an absolute path for jobs started before the asset store.
+    pub original_image_path: String,
+    /// `preprocess::PreparedImage::content_hash` of the stored original.
+    pub image_hash: Option<String>,
+    /// `preprocess::PreparedImage::perceptual_hash`, copied onto the robot.
+    pub perceptual_hash: Option<String>,
+    /// Serialized `gemini::RobotStatus`.
+    pub stats_json: Option<String>,
+    pub generated_image_path: Option<String>,
+    pub image_task_id: Option<String>,
+    pub rig_task_id: Option<String>,
+    pub idle_anim_task_id: Option<String>,
+    pub attack_anim_task_id: Option<String>,
+    /// Set once rigging/animation has failed, so a resumed job goes straight to the static fallback.
+    pub rigging_error: Option<String>,
+    pub robot_id: Option<String>,
+    pub error: Option<String>,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+}
+
+const JOB_COLUMNS: &str = "id, status, original_image_path, stats_json, generated_image_path, image_task_id, rig_task_id, idle_anim_task_id, attack_anim_task_id, rigging_error, robot_id, error, created_at_ms, updated_at_ms, image_hash, perceptual_hash";
+
+fn job_from_row(row: &rusqlite::Row) -> Result<GenerationJob> {
+    let status: String = row.get(1)?;
+    Ok(GenerationJob {
+        id: row.get(0)?,
+        status: JobStatus::parse(&status).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                1,
+                rusqlite::types::Type::Text,
+                format!("Unknown job status: {}", status).into(),
+            )
+        })?,
+        original_image_path: row.get(2)?,
+        stats_json: row.get(3)?,
+        generated_image_path: row.get(4)?,
+        image_task_id: row.get(5)?,
+        rig_task_id: row.get(6)?,
+        idle_anim_task_id: row.get(7)?,
+        attack_anim_task_id: row.get(8)?,
+        rigging_error: row.get(9)?,
+        robot_id: row.get(10)?,
+        error: row.get(11)?,
+        created_at_ms: row.get(12)?,
+        updated_at_ms: row.get(13)?,
+        image_hash: row.get(14)?,
+        perceptual_hash: row.get(15)?,
+    })
+}
+
+const ROBOT_COLUMNS: &str = "id, name, lore, hp, atk, def, original_image_path, image_path, model_path, attack_model_path, created_at, generation_time_ms, is_favorite, stats_adjusted, stat_adjustments, food_name, calories, protein_g, fiber_g, stat_formula_version, perceptual_hash";
+
+fn robot_from_row(row: &rusqlite::Row) -> Result<RobotRecord> {
+    Ok(RobotRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        lore: row.get(2)?,
+        hp: row.get(3)?,
+        atk: row.get(4)?,
+        def: row.get(5)?,
+        original_image_path: row.get(6)?,
+        image_path: row.get(7)?,
+        model_path: row.get(8)?,
+        attack_model_path: row.get(9)?,
+        created_at: row.get(10)?,
+        generation_time_ms: row.get(11)?,
+        is_favorite: row.get(12)?,
+        stats_adjusted: row.get(13)?,
+        stat_adjustments: {
+            let json: String = row.get(14)?;
+            serde_json::from_str(&json).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(14, rusqlite::types::Type::Text, Box::new(e))
+            })?
+        },
+        nutrition: Nutrition {
+            food_name: row.get(15)?,
+            calories: row.get(16)?,
+            protein: row.get(17)?,
+            fiber: row.get(18)?,
+        },
+        stat_formula_version: row.get(19)?,
+        perceptual_hash: row.get(20)?,
+    })
+}
+
+pub fn insert_robot(conn: &Connection, robot: &RobotRecord) -> AppResult<()> {
     conn.execute(
-        "INSERT INTO robots (id, name, lore, hp, atk, def, original_image_path, image_path, model_path, attack_model_path, created_at, generation_time_ms)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        "INSERT INTO robots (id, name, lore, hp, atk, def, original_image_path, image_path, model_path, attack_model_path, created_at, generation_time_ms, is_favorite, stats_adjusted, stat_adjustments, food_name, calories, protein_g, fiber_g, stat_formula_version, perceptual_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
         params![
             robot.id,
             robot.name,
@@ -58,30 +427,23 @@ pub fn insert_robot(conn: &Connection, robot: &RobotRecord) -> Result<()> {
             robot.attack_model_path,
             robot.created_at,
             robot.generation_time_ms,
+            robot.is_favorite,
+            robot.stats_adjusted,
+            serde_json::to_string(&robot.stat_adjustments)?,
+            robot.nutrition.food_name,
+            robot.nutrition.calories,
+            robot.nutrition.protein,
+            robot.nutrition.fiber,
+            robot.stat_formula_version,
+            robot.perceptual_hash,
         ],
     )?;
     Ok(())
 }
 
-pub fn get_robots(conn: &Connection) -> Result<Vec<RobotRecord>> {
-    let mut stmt =
-        conn.prepare("SELECT id, name, lore, hp, atk, def, original_image_path, image_path, model_path, attack_model_path, created_at, generation_time_ms FROM robots")?;
-    let robot_iter = stmt.query_map([], |row| {
-        Ok(RobotRecord {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            lore: row.get(2)?,
-            hp: row.get(3)?,
-            atk: row.get(4)?,
-            def: row.get(5)?,
-            original_image_path: row.get(6)?,
-            image_path: row.get(7)?,
-            model_path: row.get(8)?,
-            attack_model_path: row.get(9)?,
-            created_at: row.get(10)?,
-            generation_time_ms: row.get(11)?,
-        })
-    })?;
+pub fn get_robots(conn: &Connection) -> AppResult<Vec<RobotRecord>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM robots", ROBOT_COLUMNS))?;
+    let robot_iter = stmt.query_map([], robot_from_row)?;
 
     let mut robots = Vec::new();
     for robot in robot_iter {
@@ -89,3 +451,531 @@ pub fn get_robots(conn: &Connection) -> Result<Vec<RobotRecord>> {
     }
     Ok(robots)
 }
+
+/// `(id, perceptual_hash)` of every robot that has one.
+pub fn robot_perceptual_hashes(conn: &Connection) -> AppResult<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT id, perceptual_hash FROM robots WHERE perceptual_hash IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<Vec<_>>>()?)
+}
+
+pub fn get_robot(conn: &Connection, id: &str) -> AppResult<Option<RobotRecord>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM robots WHERE id = ?1", ROBOT_COLUMNS))?;
+    let mut rows = stmt.query_map(params![id], robot_from_row)?;
+    Ok(rows.next().transpose()?)
+}
+
+/// Apply `update` to the robot and return the updated record, or `None` if no such robot exists.
+pub fn update_robot(conn: &Connection, id: &str, update: &RobotUpdate) -> AppResult<Option<RobotRecord>> {
+    conn.execute(
+        "UPDATE robots SET
+            name = COALESCE(?2, name),
+            lore = COALESCE(?3, lore),
+            is_favorite = COALESCE(?4, is_favorite)
+         WHERE id = ?1",
+        params![id, update.name, update.lore, update.is_favorite],
+    )?;
+    get_robot(conn, id)
+}
+
+/// Point the robot's four file columns at new asset IDs.
+pub fn set_robot_assets(
+    conn: &Connection,
+    id: &str,
+    original_image_path: &str,
+    image_path: &str,
+    model_path: &str,
+    attack_model_path: &str,
+) -> AppResult<()> {
+    conn.execute(
+        "UPDATE robots SET original_image_path = ?2, image_path = ?3, model_path = ?4, attack_model_path = ?5 WHERE id = ?1",
+        params![id, original_image_path, image_path, model_path, attack_model_path],
+    )?;
+    Ok(())
+}
+
+/// Delete the robot row and return the removed record so callers can clean up its files.
+pub fn delete_robot(conn: &Connection, id: &str) -> AppResult<Option<RobotRecord>> {
+    let robot = get_robot(conn, id)?;
+    if robot.is_some() {
+        conn.execute("DELETE FROM robots WHERE id = ?1", params![id])?;
+    }
+    Ok(robot)
+}
+
+pub fn query_robots(conn: &Connection, query: &RobotQuery) -> AppResult<RobotPage> {
+    use rusqlite::types::Value;
+
+    let mut conditions: Vec<String> = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    for (column, range) in [("hp", query.hp), ("atk", query.atk), ("def", query.def)] {
+        if let Some(min) = range.min {
+            values.push(Value::Integer(min as i64));
+            conditions.push(format!("{} >= ?{}", column, values.len()));
+        }
+        if let Some(max) = range.max {
+            values.push(Value::Integer(max as i64));
+            conditions.push(format!("{} <= ?{}", column, values.len()));
+        }
+    }
+    if query.favorites_only {
+        conditions.push("is_favorite = 1".to_string());
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM robots{}", where_clause),
+        rusqlite::params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let direction = query.direction.keyword();
+    // `id` breaks ties so pages stay stable when many robots share a stat value.
+    let sql = format!(
+        "SELECT {} FROM robots{} ORDER BY {} {}, id {} LIMIT {} OFFSET {}",
+        ROBOT_COLUMNS,
+        where_clause,
+        query.sort_by.column(),
+        direction,
+        direction,
+        limit,
+        query.offset
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let robots = stmt
+        .query_map(rusqlite::params_from_iter(values.iter()), robot_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    let end = query.offset as i64 + robots.len() as i64;
+    let next_offset = if end < total { Some(end as u32) } else { None };
+
+    Ok(RobotPage {
+        robots,
+        total,
+        next_offset,
+    })
+}
+
+/// The trigram tokenizer can only match terms of at least three characters.
+const MIN_FTS_TERM_CHARS: usize = 3;
+
+/// Search robot names and lore, best matches first.
+pub fn search_robots(conn: &Connection, query: &str, limit: u32) -> AppResult<Vec<RobotSearchHit>> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+
+    if terms.iter().all(|t| t.chars().count() >= MIN_FTS_TERM_CHARS) {
+        search_robots_fts(conn, &terms, limit)
+    } else {
+        search_robots_like(conn, &terms, limit)
+    }
+}
+
+fn search_robots_fts(conn: &Connection, terms: &[&str], limit: u32) -> AppResult<Vec<RobotSearchHit>> {
+    // Quote every term so user input is never parsed as FTS5 query syntax.
+    let match_expr = terms
+        .iter()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let columns = ROBOT_COLUMNS
+        .split(", ")
+        .map(|c| format!("r.{}", c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "SELECT {},
+                highlight(robots_fts, 1, ?2, ?3) AS name_snippet,
+                snippet(robots_fts, 2, ?2, ?3, '…', 32) AS lore_snippet,
+                robots_fts.rank AS rank
+         FROM robots_fts JOIN robots r ON r.id = robots_fts.id
+         WHERE robots_fts MATCH ?1
+         ORDER BY robots_fts.rank
+         LIMIT ?4",
+        columns
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let hits = stmt
+        .query_map(params![match_expr, HIGHLIGHT_START, HIGHLIGHT_END, limit], |row| {
+            Ok(RobotSearchHit {
+                robot: robot_from_row(row)?,
+                // By name, since the robot columns before them grow with the schema.
+                name_snippet: row.get("name_snippet")?,
+                lore_snippet: row.get("lore_snippet")?,
+                rank: row.get("rank")?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(hits)
+}
+
+/// Fallback for one- and two-character terms (e.g. "寿司"), which the trigram index cannot match.
+fn search_robots_like(conn: &Connection, terms: &[&str], limit: u32) -> AppResult<Vec<RobotSearchHit>> {
+    let conditions = (1..=terms.len())
+        .map(|i| format!("(name LIKE ?{i} ESCAPE '\\' OR lore LIKE ?{i} ESCAPE '\\')"))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let patterns: Vec<String> = terms
+        .iter()
+        .map(|t| {
+            let escaped = t.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{}%", escaped)
+        })
+        .collect();
+
+    let sql = format!(
+        "SELECT {} FROM robots WHERE {} ORDER BY created_at DESC LIMIT {}",
+        ROBOT_COLUMNS, conditions, limit
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let robots = stmt
+        .query_map(rusqlite::params_from_iter(patterns.iter()), robot_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(robots
+        .into_iter()
+        .map(|robot| RobotSearchHit {
+            name_snippet: highlight_terms(&robot.name, terms),
+            lore_snippet: highlight_terms(&robot.lore, terms),
+            robot,
+            rank: 0.0,
+        })
+        .collect())
+}
+
+/// Wrap case-insensitive occurrences of any term in highlight markers.
+fn highlight_terms(text: &str, terms: &[&str]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let terms: Vec<Vec<char>> = terms.iter().map(|t| t.chars().collect()).collect();
+    let same = |a: char, b: char| a.to_lowercase().eq(b.to_lowercase());
+
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let matched = terms.iter().find(|term| {
+            !term.is_empty()
+                && i + term.len() <= chars.len()
+                && term.iter().zip(&chars[i..]).all(|(&a, &b)| same(a, b))
+        });
+        match matched {
+            Some(term) => {
+                out.push_str(HIGHLIGHT_START);
+                out.extend(&chars[i..i + term.len()]);
+                out.push_str(HIGHLIGHT_END);
+                i += term.len();
+            }
+            None => {
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+pub fn insert_job(conn: &Connection, job: &GenerationJob) -> AppResult<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO generation_jobs ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            JOB_COLUMNS
+        ),
+        params![
+            job.id,
+            job.status.as_str(),
+            job.original_image_path,
+            job.stats_json,
+            job.generated_image_path,
+            job.image_task_id,
+            job.rig_task_id,
+            job.idle_anim_task_id,
+            job.attack_anim_task_id,
+            job.rigging_error,
+            job.robot_id,
+            job.error,
+            job.created_at_ms,
+            job.updated_at_ms,
+            job.image_hash,
+            job.perceptual_hash,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Persist every mutable field of `job`.
+pub fn update_job(conn: &Connection, job: &GenerationJob) -> AppResult<()> {
+    conn.execute(
+        "UPDATE generation_jobs SET
+            status = ?2,
+            stats_json = ?3,
+            generated_image_path = ?4,
+            image_task_id = ?5,
+            rig_task_id = ?6,
+            idle_anim_task_id = ?7,
+            attack_anim_task_id = ?8,
+            rigging_error = ?9,
+            robot_id = ?10,
+            error = ?11,
+            updated_at_ms = ?12
+         WHERE id = ?1",
+        params![
+            job.id,
+            job.status.as_str(),
+            job.stats_json,
+            job.generated_image_path,
+            job.image_task_id,
+            job.rig_task_id,
+            job.idle_anim_task_id,
+            job.attack_anim_task_id,
+            job.rigging_error,
+            job.robot_id,
+            job.error,
+            job.updated_at_ms,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Jobs that were queued or running, oldest first.
+pub fn get_unfinished_jobs(conn: &Connection) -> AppResult<Vec<GenerationJob>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM generation_jobs WHERE status IN (?1, ?2) ORDER BY created_at_ms",
+        JOB_COLUMNS
+    ))?;
+    let jobs = stmt
+        .query_map(params![JobStatus::Queued.as_str(), JobStatus::Running.as_str()], job_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(jobs)
+}
+
+/// Most recent jobs first, in any status.
+pub fn list_jobs(conn: &Connection, limit: u32) -> AppResult<Vec<GenerationJob>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM generation_jobs ORDER BY created_at_ms DESC LIMIT ?1",
+        JOB_COLUMNS
+    ))?;
+    let jobs = stmt
+        .query_map(params![limit], job_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(jobs)
+}
+
+/// Insert the finished robot and mark its job completed atomically, so a crash
+/// in between can never resume the job and insert the robot twice.
+pub fn complete_job(conn: &mut Connection, job: &GenerationJob, robot: &RobotRecord) -> AppResult<()> {
+    let tx = conn.transaction()?;
+    insert_robot(&tx, robot)?;
+    update_job(&tx, job)?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema and one row as written by builds that predate `MIGRATIONS`.
+    fn baseline_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE robots (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                lore TEXT NOT NULL,
+                hp INTEGER NOT NULL,
+                atk INTEGER NOT NULL,
+                def INTEGER NOT NULL,
+                original_image_path TEXT NOT NULL,
+                image_path TEXT NOT NULL,
+                model_path TEXT NOT NULL,
+                attack_model_path TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                generation_time_ms INTEGER NOT NULL
+            );
+            INSERT INTO robots VALUES ('old', 'Ramen Titan', 'Forged in a bowl of broth', 1200, 50, 20,
+                '/data/old.png', '/data/old_concept.png', '/data/old.glb', '/data/old_attack.glb', 100, 90000);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn test_robot(id: &str, name: &str, lore: &str, hp: i32) -> RobotRecord {
+        RobotRecord {
+            id: id.to_string(),
+            name: name.to_string(),
+            lore: lore.to_string(),
+            hp,
+            atk: 50,
+            def: 20,
+            original_image_path: format!("{}/original.jpg", id),
+            image_path: format!("{}/concept.png", id),
+            model_path: format!("{}/idle.glb", id),
+            attack_model_path: format!("{}/attack.glb", id),
+            generation_time_ms: 60_000,
+            ..Default::default()
+        }
+    }
+
+    fn migrated_db() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn migrates_fresh_database_to_latest() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&mut conn).unwrap(), SCHEMA_VERSION);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(get_robots(&conn).unwrap().is_empty());
+        assert!(get_unfinished_jobs(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn upgrades_baseline_database_and_keeps_robots() {
+        let mut conn = baseline_db();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+        migrate(&mut conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        let robot = get_robot(&conn, "old").unwrap().unwrap();
+        assert_eq!(robot.name, "Ramen Titan");
+        assert_eq!(robot.hp, 1200);
+        assert!(!robot.is_favorite);
+        assert!(!robot.stats_adjusted);
+        assert!(robot.stat_adjustments.is_empty());
+        assert_eq!(robot.nutrition, Nutrition::default());
+        assert_eq!(robot.stat_formula_version, None);
+        assert_eq!(robot.perceptual_hash, None);
+
+        let indexed: i64 = conn
+            .query_row("SELECT COUNT(*) FROM robots_fts WHERE id = 'old'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(indexed, 1);
+    }
+
+    #[test]
+    fn migrate_is_idempotent() {
+        let mut conn = baseline_db();
+        migrate(&mut conn).unwrap();
+        assert_eq!(migrate(&mut conn).unwrap(), SCHEMA_VERSION);
+        assert_eq!(get_robots(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn refuses_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        let err = migrate(&mut conn).unwrap_err();
+        assert_eq!(err.from_version, SCHEMA_VERSION + 1);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION + 1);
+    }
+
+    #[test]
+    fn failed_step_keeps_last_applied_version() {
+        let mut conn = baseline_db();
+        // Step 2 adds `is_favorite`; a column already named so makes it fail.
+        conn.execute_batch("ALTER TABLE robots ADD COLUMN is_favorite INTEGER;").unwrap();
+        let err = migrate(&mut conn).unwrap_err();
+        assert_eq!(err.from_version, 0);
+        assert_eq!(err.failed_version, 2);
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+    }
+
+    #[test]
+    fn query_robots_pages_through_all_matches() {
+        let conn = migrated_db();
+        for i in 0..5 {
+            // Equal HP on every robot exercises the `id` tie-breaker.
+            insert_robot(&conn, &test_robot(&format!("robot-{}", i), "Bot", "", 1000)).unwrap();
+        }
+
+        let mut query = RobotQuery {
+            sort_by: RobotSortKey::Hp,
+            direction: SortDirection::Asc,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut seen = Vec::new();
+        loop {
+            let page = query_robots(&conn, &query).unwrap();
+            assert_eq!(page.total, 5);
+            seen.extend(page.robots.into_iter().map(|r| r.id));
+            match page.next_offset {
+                Some(offset) => query.offset = offset,
+                None => break,
+            }
+        }
+        assert_eq!(seen, ["robot-0", "robot-1", "robot-2", "robot-3", "robot-4"]);
+    }
+
+    #[test]
+    fn query_robots_filters_by_stat_and_favorite() {
+        let conn = migrated_db();
+        for (i, hp) in [600, 900, 1500].into_iter().enumerate() {
+            let mut robot = test_robot(&format!("robot-{}", i), "Bot", "", hp);
+            robot.is_favorite = i != 0;
+            insert_robot(&conn, &robot).unwrap();
+        }
+
+        let page = query_robots(
+            &conn,
+            &RobotQuery {
+                hp: StatRange { min: Some(700), max: None },
+                favorites_only: true,
+                limit: Some(MAX_PAGE_SIZE + 100),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.next_offset, None);
+        let ids: Vec<_> = page.robots.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["robot-2", "robot-1"]);
+    }
+
+    #[test]
+    fn search_finds_and_highlights_matches() {
+        let conn = migrated_db();
+        insert_robot(&conn, &test_robot("a", "Ramen Titan", "Forged in a bowl of tonkotsu broth", 1000)).unwrap();
+        insert_robot(&conn, &test_robot("b", "Sushi Sentinel", "Rivals the ramen titan in speed", 1000)).unwrap();
+        insert_robot(&conn, &test_robot("c", "Curry Crusher", "Spicy", 1000)).unwrap();
+
+        let hits = search_robots(&conn, "ramen", 10).unwrap();
+        let ids: Vec<_> = hits.iter().map(|h| h.robot.id.as_str()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"a") && ids.contains(&"b"));
+
+        let titan = hits.iter().find(|h| h.robot.id == "a").unwrap();
+        assert_eq!(titan.name_snippet, "<mark>Ramen</mark> Titan");
+        assert_eq!(titan.robot.name, "Ramen Titan");
+        let sentinel = hits.iter().find(|h| h.robot.id == "b").unwrap();
+        assert!(sentinel.lore_snippet.contains("<mark>ramen</mark>"));
+    }
+
+    #[test]
+    fn search_falls_back_to_like_for_short_terms() {
+        let conn = migrated_db();
+        insert_robot(&conn, &test_robot("a", "寿司ロボ", "Oishii Industries", 1000)).unwrap();
+        insert_robot(&conn, &test_robot("b", "Ramen Titan", "", 1000)).unwrap();
+
+        let hits = search_robots(&conn, "寿司", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].name_snippet, "<mark>寿司</mark>ロボ");
+        assert_eq!(search_robots(&conn, "oishii", 10).unwrap().len(), 1);
+        assert!(search_robots(&conn, "  ", 10).unwrap().is_empty());
+    }
+}
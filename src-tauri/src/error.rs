@@ -0,0 +1,239 @@
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where an error originated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    Gemini,
+    Meshy,
+    Database,
+    Filesystem,
+    Mock,
+}
+
+/// Stable, UI-facing error codes. Only ever add variants; the frontend matches on these strings.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    MissingApiKey,
+    Unauthorized,
+    QuotaExceeded,
+    RateLimited,
+    Timeout,
+    Network,
+    ContentRefused,
+    InvalidResponse,
+    ProviderUnavailable,
+    ProviderError,
+    TaskFailed,
+    Cancelled,
+    NotFound,
+    InvalidInput,
+    Database,
+    Io,
+    Internal,
+    /// The upload looks like the photo of an existing robot.
+    Duplicate,
+}
+
+impl ErrorCode {
+    /// Whether retrying the same request later can reasonably succeed.
+    fn default_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RateLimited
+                | ErrorCode::Timeout
+                | ErrorCode::Network
+                | ErrorCode::ProviderUnavailable
+        )
+    }
+}
+
+/// Error type returned by every backend module and Tauri command.
+#[derive(Serialize, Debug, Clone)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub retryable: bool,
+    pub provider: Option<Provider>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            retryable: code.default_retryable(),
+            provider: None,
+        }
+    }
+
+    pub fn with_provider(mut self, provider: Provider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(ErrorCode::Cancelled, "Generation cancelled")
+    }
+
+    pub fn missing_api_key(provider: Provider, var: &str) -> Self {
+        Self::new(
+            ErrorCode::MissingApiKey,
+            format!("No API key configured. Add one on the Settings page or set {}", var),
+        )
+        .with_provider(provider)
+    }
+
+    pub fn invalid_response(provider: Provider, message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidResponse, message).with_provider(provider)
+    }
+
+    /// Classify a transport-level failure (no HTTP response received).
+    pub fn from_reqwest(provider: Provider, context: &str, err: reqwest::Error) -> Self {
+        let code = if err.is_timeout() {
+            ErrorCode::Timeout
+        } else if err.is_decode() {
+            ErrorCode::InvalidResponse
+        } else {
+            ErrorCode::Network
+        };
+        // The URL can carry credentials, and this message is stored and shown in the UI.
+        Self::new(code, format!("{}: {}", context, err.without_url())).with_provider(provider)
+    }
+
+    /// Classify a non-2xx HTTP response from a provider.
+    pub fn from_http(provider: Provider, status: StatusCode, body: &str) -> Self {
+        let lower = body.to_lowercase();
+        let code = match status.as_u16() {
+            401 | 403 => ErrorCode::Unauthorized,
+            // Gemini reports an invalid key as a 400
+            400 if lower.contains("api_key_invalid") || lower.contains("api key not valid") => {
+                ErrorCode::Unauthorized
+            }
+            400 if lower.contains("safety") || lower.contains("blocked") => ErrorCode::ContentRefused,
+            // Meshy answers 402 when the account is out of credits
+            402 => ErrorCode::QuotaExceeded,
+            404 => ErrorCode::NotFound,
+            408 | 504 => ErrorCode::Timeout,
+            429 if lower.contains("quota") && !lower.contains("per minute") => ErrorCode::QuotaExceeded,
+            429 => ErrorCode::RateLimited,
+            500..=599 => ErrorCode::ProviderUnavailable,
+            _ => ErrorCode::ProviderError,
+        };
+        let name = match provider {
+            Provider::Gemini => "Gemini",
+            Provider::Meshy => "Meshy",
+            Provider::Database => "Database",
+            Provider::Filesystem => "Filesystem",
+            Provider::Mock => "Mock",
+        };
+        Self::new(code, format!("{} API Error ({}): {}", name, status, body)).with_provider(provider)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::new(ErrorCode::Database, err.to_string()).with_provider(Provider::Database)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        let code = match err.kind() {
+            std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, err.to_string()).with_provider(Provider::Filesystem)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::internal(format!("JSON error: {}", err))
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(err: tauri::Error) -> Self {
+        Self::internal(err.to_string())
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for AppError {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        Self::internal(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_http_statuses() {
+        let cases = [
+            (401, "", ErrorCode::Unauthorized, false),
+            (403, "", ErrorCode::Unauthorized, false),
+            (400, "API key not valid. Please pass a valid API key.", ErrorCode::Unauthorized, false),
+            (400, "Request blocked for SAFETY reasons", ErrorCode::ContentRefused, false),
+            (400, "bad request", ErrorCode::ProviderError, false),
+            (402, "Insufficient credits", ErrorCode::QuotaExceeded, false),
+            (404, "", ErrorCode::NotFound, false),
+            (408, "", ErrorCode::Timeout, true),
+            (429, "Quota exceeded for the day", ErrorCode::QuotaExceeded, false),
+            (429, "Quota exceeded: requests per minute", ErrorCode::RateLimited, true),
+            (429, "Too many requests", ErrorCode::RateLimited, true),
+            (500, "", ErrorCode::ProviderUnavailable, true),
+            (503, "", ErrorCode::ProviderUnavailable, true),
+            (504, "", ErrorCode::Timeout, true),
+            (418, "", ErrorCode::ProviderError, false),
+        ];
+        for (status, body, code, retryable) in cases {
+            let err = AppError::from_http(Provider::Meshy, StatusCode::from_u16(status).unwrap(), body);
+            assert_eq!(err.code, code, "{} {:?}", status, body);
+            assert_eq!(err.retryable, retryable, "{} {:?}", status, body);
+            assert_eq!(err.provider, Some(Provider::Meshy));
+        }
+    }
+
+    #[tokio::test]
+    async fn transport_errors_do_not_leak_the_url() {
+        // Bind and drop a listener so the port is known to refuse connections.
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let url = format!("http://127.0.0.1:{}/v1/models?key=secret-key", port);
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let err = client.get(&url).send().await.unwrap_err();
+
+        let err = AppError::from_reqwest(Provider::Gemini, "Failed to send Gemini request", err);
+        assert_eq!(err.code, ErrorCode::Network);
+        assert!(err.retryable);
+        assert!(err.message.starts_with("Failed to send Gemini request: "));
+        assert!(!err.message.contains("secret-key"), "{}", err.message);
+        assert!(!err.message.contains("127.0.0.1"), "{}", err.message);
+    }
+}
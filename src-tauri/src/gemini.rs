@@ -1,6 +1,86 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult, ErrorCode, Provider};
+use crate::keys::ApiKeyStore;
+use crate::settings::SettingsStore;
+use crate::stats::{Nutrition, StatAdjustment};
+use crate::providers::{ConceptImageProvider, StatsProvider};
+
+pub const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// The key goes in a header rather than `?key=`, so it never shows up in the
+/// URLs that reqwest includes in its error messages.
+const API_KEY_HEADER: &str = "x-goog-api-key";
+
+/// Gemini-backed `StatsProvider` and `ConceptImageProvider`.
+pub struct GeminiProvider {
+    /// Root of the Gemini REST API, up to and including the version segment.
+    base_url: String,
+    client: Client,
+    keys: Arc<ApiKeyStore>,
+    settings: Arc<SettingsStore>,
+}
+
+impl GeminiProvider {
+    pub fn new(
+        client: Client,
+        keys: Arc<ApiKeyStore>,
+        settings: Arc<SettingsStore>,
+        base_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client,
+            keys,
+            settings,
+        }
+    }
+
+    /// `GEMINI_API_BASE_URL` replaces `DEFAULT_BASE_URL`; it must include the
+    /// `/v1beta` segment, since request paths are appended to it directly.
+    pub fn from_env(client: Client, keys: Arc<ApiKeyStore>, settings: Arc<SettingsStore>) -> Self {
+        Self::new(client, keys, settings, env::var("GEMINI_API_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()))
+    }
+}
+
+impl GeminiProvider {
+    /// Verify a key by listing a single model, which costs no quota. Tests
+    /// `api_key` when given (a key the user has not saved yet), else the stored one.
+    pub async fn check_connection(&self, api_key: Option<&str>) -> AppResult<()> {
+        let api_key = match api_key {
+            Some(key) => key.to_string(),
+            None => self.keys.get(Provider::Gemini)?,
+        };
+        check_connection(&self.client, &self.base_url, &api_key).await
+    }
+}
+
+#[async_trait::async_trait]
+impl StatsProvider for GeminiProvider {
+    async fn generate_stats(&self, base64_image: String, mime_type: &str) -> AppResult<RobotStatus> {
+        let settings = self.settings.get();
+        generate_robot_status(
+            &self.client,
+            &self.base_url,
+            &self.keys.get(Provider::Gemini)?,
+            &settings.gemini.stats_model,
+            base64_image,
+            mime_type,
+        )
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl ConceptImageProvider for GeminiProvider {
+    async fn generate_image(&self, prompt: String) -> AppResult<String> {
+        let model = self.settings.get().gemini.image_model;
+        generate_robot_image(&self.client, &self.base_url, &self.keys.get(Provider::Gemini)?, &model, prompt).await
+    }
+}
 
 #[derive(Serialize)]
 struct GenerateContentRequest {
@@ -43,24 +123,78 @@ pub struct RobotStatus {
     pub name: String,
     #[serde(alias = "Lore", alias = "lore")]
     pub lore: String,
-    #[serde(alias = "HP", alias = "hp")]
+    /// HP/ATK/DEF are computed from `nutrition` by `stats::apply_formula`;
+    /// anything the model sends here is overwritten.
+    #[serde(default, alias = "HP", alias = "hp")]
     pub hp: i32,
-    #[serde(alias = "ATK", alias = "atk")]
+    #[serde(default, alias = "ATK", alias = "atk")]
     pub atk: i32,
-    #[serde(alias = "DEF", alias = "def")]
+    #[serde(default, alias = "DEF", alias = "def")]
     pub def: i32,
     #[serde(alias = "VisualDescription", alias = "visualDescription", alias = "visual_description")]
     pub visual_description: String,
+    /// Sent flat by the model, alongside the stats.
+    #[serde(flatten)]
+    pub nutrition: Nutrition,
+    /// Corrections made after generation; see `stats::apply_formula`.
+    #[serde(default)]
+    pub adjustments: Vec<StatAdjustment>,
+    /// `stats::FORMULA_VERSION` that produced the stats, or `None` if the model chose them.
+    #[serde(default)]
+    pub formula_version: Option<u32>,
 }
 
 #[derive(Deserialize, Debug)]
 struct GenerateContentResponse {
+    #[serde(default)]
     candidates: Vec<Candidate>,
+    #[serde(rename = "promptFeedback")]
+    prompt_feedback: Option<PromptFeedback>,
 }
 
 #[derive(Deserialize, Debug)]
 struct Candidate {
-    content: CandidateContent,
+    content: Option<CandidateContent>,
+    #[serde(rename = "finishReason")]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PromptFeedback {
+    #[serde(rename = "blockReason")]
+    block_reason: Option<String>,
+}
+
+/// Finish reasons Gemini uses when it refuses to produce content.
+const REFUSAL_FINISH_REASONS: &[&str] = &[
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+    "IMAGE_SAFETY",
+];
+
+/// Detect a content-safety refusal, which Gemini reports inside a 200 response.
+fn check_refusal(
+    prompt_feedback: &Option<PromptFeedback>,
+    finish_reason: Option<&str>,
+) -> AppResult<()> {
+    if let Some(reason) = prompt_feedback.as_ref().and_then(|f| f.block_reason.as_deref()) {
+        return Err(AppError::new(
+            ErrorCode::ContentRefused,
+            format!("Gemini blocked the request: {}", reason),
+        )
+        .with_provider(Provider::Gemini));
+    }
+    if let Some(reason) = finish_reason.filter(|r| REFUSAL_FINISH_REASONS.contains(r)) {
+        return Err(AppError::new(
+            ErrorCode::ContentRefused,
+            format!("Gemini refused to generate content: {}", reason),
+        )
+        .with_provider(Provider::Gemini));
+    }
+    Ok(())
 }
 
 #[derive(Deserialize, Debug)]
@@ -73,16 +207,35 @@ struct CandidatePart {
     text: String,
 }
 
-pub async fn generate_robot_status(base64_image: String) -> Result<RobotStatus, String> {
-    let api_key = env::var("GEMINI_API_KEY")
-        .map_err(|_| "GEMINI_API_KEY not found in environment variables".to_string())?;
+/// List a single model, which succeeds only with a valid key.
+pub async fn check_connection(client: &Client, base_url: &str, api_key: &str) -> AppResult<()> {
+    let url = format!("{}/models?pageSize=1", base_url);
+    let res = client
+        .get(&url)
+        .header(API_KEY_HEADER, api_key)
+        .send()
+        .await
+        .map_err(|e| AppError::from_reqwest(Provider::Gemini, "Failed to reach Gemini", e))?;
 
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent?key={}",
-        api_key
-    );
+    if !res.status().is_success() {
+        let status = res.status();
+        let error_text = res.text().await.unwrap_or_default();
+        return Err(AppError::from_http(Provider::Gemini, status, &error_text));
+    }
+    Ok(())
+}
 
-    let prompt = "この食べ物画像のカロリー、タンパク質、食物繊維を推定し、HP(500-2000), ATK(10-100), DEF(5-50)を算出し、架空の企業『オイシイ・インダストリー』が作った兵器という設定の概要(Lore)、ロボット名(Name)、および次の機能で使う画像生成AI(Text-to-Image)に入力するための、この食べ物をモチーフにしたメカニカルな戦闘ロボットの「詳細な外観プロンプト(VisualDescription英語)」を考えて、以下のスキーマの平坦なJSONのみを出力してください。\n\n※重要: プロンプト（VisualDescription）には、必ず「全身像であること（full body standing）」「頭の先から足先まで完全にフレーム内に収まっていること（extreme full body shot, feet completely visible）」を英語で明記してください。\n\n{\"name\": \"名前\", \"lore\": \"設定\", \"hp\": 1000, \"atk\": 50, \"def\": 20, \"visual_description\": \"プロンプト\"}";
+pub async fn generate_robot_status(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    base64_image: String,
+    mime_type: &str,
+) -> AppResult<RobotStatus> {
+    let url = format!("{}/models/{}:generateContent", base_url, model);
+
+    let prompt = "この食べ物画像に写っている料理名(food_name)を特定し、カロリー(calories, kcal)、タンパク質(protein, g)、食物繊維(fiber, g)を推定し、架空の企業『オイシイ・インダストリー』が作った兵器という設定の概要(Lore)、ロボット名(Name)、および次の機能で使う画像生成AI(Text-to-Image)に入力するための、この食べ物をモチーフにしたメカニカルな戦闘ロボットの「詳細な外観プロンプト(VisualDescription英語)」を考えて、以下のスキーマの平坦なJSONのみを出力してください。\n\n※重要: プロンプト（VisualDescription）には、必ず「全身像であること（full body standing）」「頭の先から足先まで完全にフレーム内に収まっていること（extreme full body shot, feet completely visible）」を英語で明記してください。\n\n{\"food_name\": \"料理名\", \"calories\": 650, \"protein\": 25.0, \"fiber\": 3.5, \"name\": \"名前\", \"lore\": \"設定\", \"visual_description\": \"プロンプト\"}";
 
     let request_body = GenerateContentRequest {
         contents: vec![Content {
@@ -92,7 +245,7 @@ pub async fn generate_robot_status(base64_image: String) -> Result<RobotStatus,
                 },
                 Part::InlineData {
                     inline_data: InlineData {
-                        mime_type: "image/png".to_string(), // または jpeg
+                        mime_type: mime_type.to_string(),
                         data: base64_image,
                     },
                 },
@@ -103,40 +256,49 @@ pub async fn generate_robot_status(base64_image: String) -> Result<RobotStatus,
         }),
     };
 
-    let client = Client::new();
     let res = client
         .post(&url)
+        .header(API_KEY_HEADER, api_key)
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Gemini, "Failed to send request", e))?;
 
     if !res.status().is_success() {
+        let status = res.status();
         let error_text = res.text().await.unwrap_or_default();
-        return Err(format!("Gemini API Error: {}", error_text));
+        return Err(AppError::from_http(Provider::Gemini, status, &error_text));
     }
 
     let response_data: GenerateContentResponse = res
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response JSON: {}", e))?;
+        .map_err(|e| AppError::invalid_response(Provider::Gemini, format!("Failed to parse response JSON: {}", e)))?;
+
+    let candidate = response_data.candidates.first();
+    check_refusal(
+        &response_data.prompt_feedback,
+        candidate.and_then(|c| c.finish_reason.as_deref()),
+    )?;
 
-    let text = &response_data
-        .candidates
-        .get(0)
-        .ok_or("No candidates returned")?
+    let text = &candidate
+        .ok_or_else(|| AppError::invalid_response(Provider::Gemini, "No candidates returned"))?
         .content
-        .parts
-        .get(0)
-        .ok_or("No parts returned")?
+        .as_ref()
+        .and_then(|c| c.parts.first())
+        .ok_or_else(|| AppError::invalid_response(Provider::Gemini, "No parts returned"))?
         .text;
 
     let status = match serde_json::from_str::<RobotStatus>(text) {
         Ok(s) => s,
         Err(e) => {
             // Fallback: search for keys if the JSON is nested
-            let v: serde_json::Value = serde_json::from_str(text)
-                .map_err(|e2| format!("Failed to parse JSON: {} (orig: {}) Text: {}", e2, e, text))?;
+            let v: serde_json::Value = serde_json::from_str(text).map_err(|e2| {
+                AppError::invalid_response(
+                    Provider::Gemini,
+                    format!("Failed to parse JSON: {} (orig: {}) Text: {}", e2, e, text),
+                )
+            })?;
             
             fn find_string(v: &serde_json::Value, keys: &[&str]) -> Option<String> {
                 if let Some(obj) = v.as_object() {
@@ -154,15 +316,15 @@ pub async fn generate_robot_status(base64_image: String) -> Result<RobotStatus,
                 None
             }
 
-            fn find_i32(v: &serde_json::Value, keys: &[&str]) -> Option<i32> {
+            fn find_f64(v: &serde_json::Value, keys: &[&str]) -> Option<f64> {
                 if let Some(obj) = v.as_object() {
                     for (k, val) in obj {
                         if keys.contains(&k.to_lowercase().as_str()) {
-                            if let Some(n) = val.as_i64() {
-                                return Some(n as i32);
+                            if let Some(n) = val.as_f64() {
+                                return Some(n);
                             }
                         }
-                        if let Some(res) = find_i32(val, keys) {
+                        if let Some(res) = find_f64(val, keys) {
                             return Some(res);
                         }
                     }
@@ -170,20 +332,39 @@ pub async fn generate_robot_status(base64_image: String) -> Result<RobotStatus,
                 None
             }
 
-            let name = find_string(&v, &["name"]).unwrap_or_else(|| "Unknown Robot".to_string());
-            let lore = find_string(&v, &["lore"]).unwrap_or_else(|| "No lore available.".to_string());
-            let visual_description = find_string(&v, &["visual_description", "visual_description_en"]).unwrap_or_else(|| "A standard mechanical combat robot.".to_string());
-            let hp = find_i32(&v, &["hp"]).unwrap_or(1000);
-            let atk = find_i32(&v, &["atk"]).unwrap_or(50);
-            let def = find_i32(&v, &["def"]).unwrap_or(20);
+            // Record every field we had to invent so the robot can be flagged.
+            let mut adjustments = Vec::new();
+            let mut or_default = |field: &str, value: Option<String>, default: &str| {
+                value.unwrap_or_else(|| {
+                    adjustments.push(StatAdjustment::defaulted(field));
+                    default.to_string()
+                })
+            };
+            let name = or_default("name", find_string(&v, &["name"]), "Unknown Robot");
+            let lore = or_default("lore", find_string(&v, &["lore"]), "No lore available.");
+            let visual_description = or_default(
+                "visual_description",
+                find_string(&v, &["visual_description", "visual_description_en"]),
+                "A standard mechanical combat robot.",
+            );
+            // Missing nutrition values stay missing; `stats::apply_formula` records them.
+            let nutrition = Nutrition {
+                food_name: find_string(&v, &["food_name", "foodname"]),
+                calories: find_f64(&v, &["calories", "calories_kcal"]),
+                protein: find_f64(&v, &["protein", "protein_g"]),
+                fiber: find_f64(&v, &["fiber", "fiber_g"]),
+            };
 
             RobotStatus {
                 name,
                 lore,
-                hp,
-                atk,
-                def,
+                hp: 0,
+                atk: 0,
+                def: 0,
                 visual_description,
+                nutrition,
+                adjustments,
+                formula_version: None,
             }
         }
     };
@@ -193,12 +374,17 @@ pub async fn generate_robot_status(base64_image: String) -> Result<RobotStatus,
 
 #[derive(Deserialize, Debug)]
 struct GenerateImageContentResponse {
+    #[serde(default)]
     candidates: Vec<ImageCandidate>,
+    #[serde(rename = "promptFeedback")]
+    prompt_feedback: Option<PromptFeedback>,
 }
 
 #[derive(Deserialize, Debug)]
 struct ImageCandidate {
-    content: ImageCandidateContent,
+    content: Option<ImageCandidateContent>,
+    #[serde(rename = "finishReason")]
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -217,15 +403,14 @@ struct ImageInlineData {
     pub data: String,
 }
 
-pub async fn generate_robot_image(prompt: String) -> Result<String, String> {
-    let api_key = env::var("GEMINI_API_KEY")
-        .map_err(|_| "GEMINI_API_KEY not found".to_string())?;
-
-    // Use nano-banana-pro-preview instead of imagen-3
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/nano-banana-pro-preview:generateContent?key={}",
-        api_key
-    );
+pub async fn generate_robot_image(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    prompt: String,
+) -> AppResult<String> {
+    let url = format!("{}/models/{}:generateContent", base_url, model);
 
     let instruction_prompt = format!("{}, highly zoomed out, full A-pose with slightly spread arms. The ENTIRE body from the top of the head to the bottom of the feet MUST be completely visible inside the frame. Leave plenty of empty white space around the character. DO NOT crop the image at the ankles or head. single white background `#FFFFFF`, mechanical combat robot design, clear silhouette.", prompt);
 
@@ -240,31 +425,37 @@ pub async fn generate_robot_image(prompt: String) -> Result<String, String> {
         generation_config: None, // NanoBanana doesn't need responseMimeType
     };
 
-    let client = Client::new();
     let res = client
         .post(&url)
+        .header(API_KEY_HEADER, api_key)
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Gemini, "Failed to send request", e))?;
 
     if !res.status().is_success() {
+        let status = res.status();
         let error_text = res.text().await.unwrap_or_default();
-        return Err(format!("NanoBanana API Error: {}", error_text));
+        return Err(AppError::from_http(Provider::Gemini, status, &error_text));
     }
 
     let response_data: GenerateImageContentResponse = res
         .json()
         .await
-        .map_err(|e| format!("Failed to parse NanoBanana response JSON: {}", e))?;
-
-    let b64 = response_data
-        .candidates
-        .get(0)
-        .and_then(|c| c.content.parts.get(0))
-        .and_then(|p| p.inline_data.as_ref())
+        .map_err(|e| AppError::invalid_response(Provider::Gemini, format!("Failed to parse NanoBanana response JSON: {}", e)))?;
+
+    let candidate = response_data.candidates.first();
+    check_refusal(
+        &response_data.prompt_feedback,
+        candidate.and_then(|c| c.finish_reason.as_deref()),
+    )?;
+
+    // The image may follow a text part, so take the first part that carries data.
+    let b64 = candidate
+        .and_then(|c| c.content.as_ref())
+        .and_then(|c| c.parts.iter().find_map(|p| p.inline_data.as_ref()))
         .map(|d| d.data.clone())
-        .ok_or("No image data returned from NanoBanana API")?;
+        .ok_or_else(|| AppError::invalid_response(Provider::Gemini, "No image data returned from NanoBanana API"))?;
 
     Ok(b64)
 }
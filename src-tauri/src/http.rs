@@ -0,0 +1,68 @@
+//! The one HTTP client used for every provider request. It is built once at
+//! startup and shared through Tauri state, so connections are pooled and no
+//! request can hang forever.
+
+use reqwest::{Client, Proxy};
+use std::env;
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    /// Maximum silence between two reads, not a limit on the whole response,
+    /// so large GLB downloads are fine as long as bytes keep arriving.
+    pub read_timeout: Duration,
+    /// Proxy for all requests. When unset, reqwest still honors the usual
+    /// `HTTPS_PROXY` / `ALL_PROXY` environment variables.
+    pub proxy: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(120),
+            proxy: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Reads `FFR_HTTP_CONNECT_TIMEOUT_SECS`, `FFR_HTTP_READ_TIMEOUT_SECS` and `FFR_HTTP_PROXY`.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let secs = |var: &str, fallback: Duration| {
+            env::var(var)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(fallback)
+        };
+        Self {
+            connect_timeout: secs("FFR_HTTP_CONNECT_TIMEOUT_SECS", default.connect_timeout),
+            read_timeout: secs("FFR_HTTP_READ_TIMEOUT_SECS", default.read_timeout),
+            proxy: env::var("FFR_HTTP_PROXY").ok().filter(|p| !p.trim().is_empty()),
+        }
+    }
+}
+
+pub fn build_client(config: &HttpConfig) -> AppResult<Client> {
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(config.connect_timeout)
+        .read_timeout(config.read_timeout);
+
+    if let Some(proxy) = &config.proxy {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| AppError::invalid_input(format!("Invalid proxy URL {}: {}", proxy, e)))?;
+        builder = builder.proxy(proxy);
+    }
+
+    builder
+        .build()
+        .map_err(|e| AppError::internal(format!("Failed to build HTTP client: {}", e)))
+}
@@ -0,0 +1,416 @@
+//! Provider API keys entered from inside the app.
+//!
+//! Keys are sealed with ChaCha20-Poly1305 into `credentials.bin` in the app
+//! data directory. The random data key lives next to it in `credentials.key`
+//! (owner-only on Unix), so this keeps keys out of plain text, backups of the
+//! database and casual `grep`s; it does not protect against someone who can
+//! read the user's files. `GEMINI_API_KEY` / `MESHY_AI_API_KEY` still take
+//! precedence, so `.env` keeps working for development.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::error::{AppError, AppResult, ErrorCode, Provider};
+use crate::gemini::GeminiProvider;
+use crate::meshy::MeshyProvider;
+use crate::settings::SettingsStore;
+
+const NONCE_LEN: usize = 12;
+
+/// Where the key currently in effect for a provider comes from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    Env,
+    Stored,
+    Missing,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ApiKeyStatus {
+    pub provider: Provider,
+    pub source: KeySource,
+    /// Last four characters of the key in effect, for display.
+    pub hint: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StoredKeys {
+    gemini: Option<String>,
+    meshy: Option<String>,
+}
+
+fn no_api_key(provider: Provider) -> AppError {
+    AppError::invalid_input(format!("{:?} does not use an API key", provider))
+}
+
+impl StoredKeys {
+    fn slot(&mut self, provider: Provider) -> AppResult<&mut Option<String>> {
+        match provider {
+            Provider::Gemini => Ok(&mut self.gemini),
+            Provider::Meshy => Ok(&mut self.meshy),
+            other => Err(no_api_key(other)),
+        }
+    }
+}
+
+/// Environment variable that overrides the stored key.
+pub fn env_var(provider: Provider) -> AppResult<&'static str> {
+    match provider {
+        Provider::Gemini => Ok("GEMINI_API_KEY"),
+        Provider::Meshy => Ok("MESHY_AI_API_KEY"),
+        other => Err(no_api_key(other)),
+    }
+}
+
+fn env_override(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|k| !k.trim().is_empty())
+}
+
+/// Reject obviously broken input (pasted whitespace, quotes, truncation) before
+/// it is stored or sent anywhere.
+pub fn validate_format(provider: Provider, key: &str) -> AppResult<()> {
+    env_var(provider)?;
+    if key.is_empty() {
+        return Err(AppError::invalid_input("API key is empty"));
+    }
+    if key.len() < 16 || key.len() > 256 {
+        return Err(AppError::invalid_input("API key has an unexpected length"));
+    }
+    if !key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return Err(AppError::invalid_input("API key contains unexpected characters"));
+    }
+    Ok(())
+}
+
+/// Encrypted key store, held in Tauri state and shared with the providers.
+pub struct ApiKeyStore {
+    data_path: PathBuf,
+    key_path: PathBuf,
+    keys: Mutex<StoredKeys>,
+}
+
+impl ApiKeyStore {
+    /// Open the store in `dir`. An unreadable or tampered file is reported and
+    /// treated as empty, so the user can simply enter the keys again.
+    pub fn open(dir: &Path) -> Self {
+        let mut store = Self {
+            data_path: dir.join("credentials.bin"),
+            key_path: dir.join("credentials.key"),
+            keys: Mutex::new(StoredKeys::default()),
+        };
+        match store.load() {
+            Ok(keys) => store.keys = Mutex::new(keys),
+            Err(e) => eprintln!("Failed to read stored API keys: {}", e),
+        }
+        store
+    }
+
+    /// Cipher for the key file, generating the file first when `create` is set.
+    fn cipher(&self, create: bool) -> AppResult<ChaCha20Poly1305> {
+        match fs::read(&self.key_path) {
+            Ok(bytes) if bytes.len() == 32 => Ok(ChaCha20Poly1305::new(Key::from_slice(&bytes))),
+            Ok(_) => Err(AppError::internal("Credential key file is corrupted")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                write_private(&self.key_path, key.as_slice())?;
+                Ok(ChaCha20Poly1305::new(&key))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(AppError::internal("Credential key file is missing"))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn load(&self) -> AppResult<StoredKeys> {
+        let sealed = match fs::read(&self.data_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(StoredKeys::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let cipher = self.cipher(false)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(AppError::internal("Credential file is corrupted"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plain = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::internal("Credential file could not be decrypted"))?;
+        Ok(serde_json::from_slice(&plain)?)
+    }
+
+    fn save(&self, keys: &StoredKeys) -> AppResult<()> {
+        let cipher = self.cipher(true)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plain = serde_json::to_vec(keys)?;
+        let ciphertext = cipher
+            .encrypt(&nonce, plain.as_slice())
+            .map_err(|_| AppError::internal("Failed to encrypt API keys"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        write_private(&self.data_path, &sealed)
+    }
+
+    /// The key to use for `provider`: the environment variable if set, else the stored key.
+    pub fn get(&self, provider: Provider) -> AppResult<String> {
+        let var = env_var(provider)?;
+        if let Some(key) = env_override(var) {
+            return Ok(key);
+        }
+        self.keys
+            .lock()?
+            .slot(provider)?
+            .clone()
+            .ok_or_else(|| AppError::missing_api_key(provider, var))
+    }
+
+    pub fn status(&self, provider: Provider) -> AppResult<ApiKeyStatus> {
+        let var = env_var(provider)?;
+        let (source, key) = match env_override(var) {
+            Some(key) => (KeySource::Env, Some(key)),
+            None => match self.keys.lock()?.slot(provider)?.clone() {
+                Some(key) => (KeySource::Stored, Some(key)),
+                None => (KeySource::Missing, None),
+            },
+        };
+        Ok(ApiKeyStatus {
+            provider,
+            source,
+            hint: key.map(|k| k.chars().skip(k.chars().count().saturating_sub(4)).collect()),
+        })
+    }
+
+    pub fn set(&self, provider: Provider, key: &str) -> AppResult<()> {
+        let key = key.trim();
+        validate_format(provider, key)?;
+        let mut keys = self.keys.lock()?;
+        let previous = keys.slot(provider)?.replace(key.to_string());
+        if let Err(e) = self.save(&keys) {
+            *keys.slot(provider)? = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self, provider: Provider) -> AppResult<()> {
+        let mut keys = self.keys.lock()?;
+        if keys.slot(provider)?.take().is_some() {
+            self.save(&keys)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_private(path: &Path, bytes: &[u8]) -> AppResult<()> {
+    // Append rather than replace the extension: `credentials.key` and
+    // `credentials.bin` must not share a temp file.
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Owner-only from the start, so the key is never readable under the umask.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    // `mode` only applies on creation; tighten a temp file left over from a crash.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path).map_err(|e| {
+        AppError::new(ErrorCode::Io, format!("Failed to save {}: {}", path.display(), e))
+            .with_provider(Provider::Filesystem)
+    })
+}
+
+/// Providers that need an API key, in display order.
+pub const KEYED_PROVIDERS: [Provider; 2] = [Provider::Gemini, Provider::Meshy];
+
+/// Result of one `test_connections` probe.
+#[derive(Serialize, Debug, Clone)]
+pub struct ConnectionCheck {
+    pub provider: Provider,
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub error: Option<AppError>,
+}
+
+/// Make one authenticated request to `provider`, with `candidate` if given,
+/// otherwise with the key currently in effect.
+pub async fn check_connection(
+    client: &Client,
+    keys: &Arc<ApiKeyStore>,
+    settings: &Arc<SettingsStore>,
+    provider: Provider,
+    candidate: Option<&str>,
+) -> AppResult<()> {
+    match provider {
+        Provider::Gemini => {
+            GeminiProvider::from_env(client.clone(), keys.clone(), settings.clone())
+                .check_connection(candidate)
+                .await
+        }
+        Provider::Meshy => {
+            MeshyProvider::from_env(client.clone(), keys.clone(), settings.clone())
+                .check_connection(candidate)
+                .await
+        }
+        other => Err(no_api_key(other)),
+    }
+}
+
+pub async fn test_connections(
+    client: &Client,
+    keys: &Arc<ApiKeyStore>,
+    settings: &Arc<SettingsStore>,
+) -> Vec<ConnectionCheck> {
+    let probe = |provider| async move {
+        let started = Instant::now();
+        let result = check_connection(client, keys, settings, provider, None).await;
+        ConnectionCheck {
+            provider,
+            ok: result.is_ok(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            error: result.err(),
+        }
+    };
+    let (gemini, meshy) = tokio::join!(probe(Provider::Gemini), probe(Provider::Meshy));
+    vec![gemini, meshy]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "msy_0123456789abcdefABCDEF";
+
+    fn stored(store: &ApiKeyStore, provider: Provider) -> Option<String> {
+        store.keys.lock().unwrap().slot(provider).unwrap().clone()
+    }
+
+    #[test]
+    fn keys_survive_a_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        ApiKeyStore::open(dir.path()).set(Provider::Meshy, &format!("  {}\n", KEY)).unwrap();
+
+        let sealed = fs::read(dir.path().join("credentials.bin")).unwrap();
+        assert!(!sealed.windows(KEY.len()).any(|w| w == KEY.as_bytes()));
+
+        let reopened = ApiKeyStore::open(dir.path());
+        assert_eq!(reopened.load().unwrap().meshy.as_deref(), Some(KEY));
+        assert_eq!(stored(&reopened, Provider::Meshy).as_deref(), Some(KEY));
+        assert_eq!(stored(&reopened, Provider::Gemini), None);
+    }
+
+    #[test]
+    fn damaged_credentials_are_errors_not_panics() {
+        let dir = tempfile::tempdir().unwrap();
+        ApiKeyStore::open(dir.path()).set(Provider::Meshy, KEY).unwrap();
+        let data_path = dir.path().join("credentials.bin");
+        let sealed = fs::read(&data_path).unwrap();
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        fs::write(&data_path, &tampered).unwrap();
+        let store = ApiKeyStore::open(dir.path());
+        assert!(store.load().is_err());
+        assert_eq!(stored(&store, Provider::Meshy), None);
+
+        fs::write(&data_path, &sealed[..NONCE_LEN - 1]).unwrap();
+        assert!(ApiKeyStore::open(dir.path()).load().is_err());
+
+        fs::write(&data_path, &sealed).unwrap();
+        fs::write(dir.path().join("credentials.key"), b"short").unwrap();
+        assert!(ApiKeyStore::open(dir.path()).load().is_err());
+
+        fs::remove_file(dir.path().join("credentials.key")).unwrap();
+        assert!(ApiKeyStore::open(dir.path()).load().is_err());
+    }
+
+    #[test]
+    fn env_var_takes_precedence_over_stored_key() {
+        // The only test that touches GEMINI_API_KEY, so it cannot race with the others.
+        let dir = tempfile::tempdir().unwrap();
+        let store = ApiKeyStore::open(dir.path());
+        store.set(Provider::Gemini, KEY).unwrap();
+
+        env::set_var("GEMINI_API_KEY", "AIzaEnvironmentKey0000");
+        assert_eq!(store.get(Provider::Gemini).unwrap(), "AIzaEnvironmentKey0000");
+        assert_eq!(store.status(Provider::Gemini).unwrap().source, KeySource::Env);
+
+        env::set_var("GEMINI_API_KEY", "  ");
+        assert_eq!(store.get(Provider::Gemini).unwrap(), KEY);
+
+        env::remove_var("GEMINI_API_KEY");
+        let status = store.status(Provider::Gemini).unwrap();
+        assert_eq!(status.source, KeySource::Stored);
+        assert_eq!(status.hint.as_deref(), Some("CDEF"));
+
+        store.clear(Provider::Gemini).unwrap();
+        let err = store.get(Provider::Gemini).unwrap_err();
+        assert_eq!(err.code, ErrorCode::MissingApiKey);
+    }
+
+    #[test]
+    fn clear_removes_only_that_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ApiKeyStore::open(dir.path());
+        store.set(Provider::Meshy, KEY).unwrap();
+        store.set(Provider::Gemini, KEY).unwrap();
+
+        store.clear(Provider::Meshy).unwrap();
+        assert_eq!(stored(&store, Provider::Meshy), None);
+        let reopened = ApiKeyStore::open(dir.path());
+        assert_eq!(stored(&reopened, Provider::Meshy), None);
+        assert_eq!(stored(&reopened, Provider::Gemini).as_deref(), Some(KEY));
+
+        // Clearing an empty slot is a no-op.
+        store.clear(Provider::Meshy).unwrap();
+    }
+
+    #[test]
+    fn validate_format_rejects_broken_keys() {
+        validate_format(Provider::Meshy, KEY).unwrap();
+        for bad in ["", "short", &"a".repeat(257), "msy_0123456789 abcdef", "\"msy_0123456789abcdef\""] {
+            assert!(validate_format(Provider::Meshy, bad).is_err(), "{:?}", bad);
+        }
+        assert!(validate_format(Provider::Database, KEY).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = ApiKeyStore::open(dir.path());
+        assert!(store.set(Provider::Meshy, "short").is_err());
+        assert!(!dir.path().join("credentials.bin").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn credential_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        ApiKeyStore::open(dir.path()).set(Provider::Meshy, KEY).unwrap();
+        for name in ["credentials.bin", "credentials.key"] {
+            let mode = fs::metadata(dir.path().join(name)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", name);
+        }
+    }
+}
@@ -1,9 +1,27 @@
+mod assets;
+mod clock;
+mod error;
 mod gemini;
+mod http;
+mod keys;
+mod maintenance;
 mod meshy;
+mod mock;
+mod package;
 mod db;
+mod pipeline;
+mod preprocess;
+mod progress;
+mod providers;
+mod settings;
+mod stats;
+#[cfg(test)]
+mod test_support;
 
-use std::sync::Mutex;
-use tauri::{Manager, Emitter};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use error::{AppError, AppResult};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -12,146 +30,264 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn test_gemini_status(base64_image: String) -> Result<gemini::RobotStatus, String> {
-    gemini::generate_robot_status(base64_image).await
+async fn test_gemini_status(
+    providers: tauri::State<'_, providers::Providers>,
+    settings: tauri::State<'_, Arc<settings::SettingsStore>>,
+    base64_image: String,
+) -> AppResult<gemini::RobotStatus> {
+    let image = pipeline::prepare_upload(base64_image).await?;
+    let mut status = providers.stats.generate_stats(image.to_base64(), image.mime_type()).await?;
+    stats::finalize(&mut status, &settings.get().stats)?;
+    Ok(status)
 }
 
 #[tauri::command]
-async fn test_imagen_generate(prompt: String) -> Result<String, String> {
-    gemini::generate_robot_image(prompt).await
+async fn test_imagen_generate(providers: tauri::State<'_, providers::Providers>, prompt: String) -> AppResult<String> {
+    providers.image.generate_image(prompt).await
 }
 
+/// Run image-to-3D alone and return the asset ID of the GLB. The model is kept
+/// under a scratch owner no robot references, so maintenance cleans it up.
 #[tauri::command]
-async fn test_meshy_generate(app: tauri::AppHandle, base64_image: String) -> Result<String, String> {
-    let task_id = meshy::create_image_to_3d_task(base64_image).await?;
-    let glb_url = meshy::poll_for_glb_url(&app, task_id.clone()).await?;
-    let filename = format!("{}.glb", task_id);
-    meshy::download_glb(app, glb_url, filename).await
+async fn test_meshy_generate(
+    app: tauri::AppHandle,
+    providers: tauri::State<'_, providers::Providers>,
+    assets: tauri::State<'_, assets::AssetStore>,
+    base64_image: String,
+) -> AppResult<String> {
+    let image = pipeline::prepare_upload(base64_image).await?;
+    let task_id = providers.mesh.create_mesh_task(image.to_base64(), image.mime_type()).await?;
+    let progress = progress::ProgressReporter::new(&app, &task_id, clock::now_ms());
+    let glb_url = providers.mesh.wait_for_mesh(&progress, &task_id).await?;
+    let bytes = providers.mesh.download_model(&glb_url).await?;
+    assets.put(&format!("test-{}", task_id), &bytes, "glb")
 }
 
 #[tauri::command]
-fn get_all_robots(state: tauri::State<'_, Mutex<rusqlite::Connection>>) -> Result<Vec<db::RobotRecord>, String> {
-    let conn = state.lock().map_err(|e| e.to_string())?;
-    db::get_robots(&conn).map_err(|e| e.to_string())
+fn get_all_robots(state: tauri::State<'_, Mutex<rusqlite::Connection>>) -> AppResult<Vec<db::RobotRecord>> {
+    let conn = state.lock()?;
+    db::get_robots(&conn)
 }
 
 #[tauri::command]
-async fn run_generation_pipeline(
-    app: tauri::AppHandle,
+fn query_robots(
     state: tauri::State<'_, Mutex<rusqlite::Connection>>,
-    base64_image: String,
-) -> Result<db::RobotRecord, String> {
-    let start_time = std::time::SystemTime::now();
+    query: db::RobotQuery,
+) -> AppResult<db::RobotPage> {
+    let conn = state.lock()?;
+    db::query_robots(&conn, &query)
+}
 
-    // Strip data URI prefix (e.g. "data:image/png;base64,") so every consumer gets clean base64
-    let clean_base64 = if base64_image.contains(",") {
-        base64_image.split(",").last().unwrap_or("").to_string()
-    } else {
-        base64_image.clone()
-    };
+#[tauri::command]
+fn search_robots(
+    state: tauri::State<'_, Mutex<rusqlite::Connection>>,
+    query: String,
+    limit: Option<u32>,
+) -> AppResult<Vec<db::RobotSearchHit>> {
+    let conn = state.lock()?;
+    db::search_robots(&conn, &query, limit.unwrap_or(db::DEFAULT_PAGE_SIZE))
+}
 
-    let _ = app.emit("pipeline-progress", "Analyzing food and generating stats...");
-    let stats = gemini::generate_robot_status(clean_base64.clone()).await?;
-    
-    // We can emit partial stats to UI
-    let _ = app.emit("pipeline-stats", stats.clone());
-
-    let _ = app.emit("pipeline-progress", "Generating robot concept image...");
-    let gen_image_b64 = gemini::generate_robot_image(stats.visual_description.clone()).await?;
-    let _ = app.emit("pipeline-progress", "Submitting 3D Generation Task to Meshy...");
-    let task_id = meshy::create_image_to_3d_task(gen_image_b64.clone()).await?;
-    
-    // Keep base GLB URL for fallback when rigging/animation fails.
-    let base_glb_url = meshy::poll_for_glb_url(&app, task_id.clone()).await?;
-
-    let app_data_dir = app.path().app_data_dir().unwrap();
-    let original_image_filename = format!("{}_original.png", task_id);
-    let generated_image_filename = format!("{}_gen.png", task_id);
-    let original_image_path = app_data_dir.join(&original_image_filename);
-    let generated_image_path = app_data_dir.join(&generated_image_filename);
-    
-    use base64::{engine::general_purpose::STANDARD, Engine as _};
-    
-    // `clean_base64` was already stripped of the data URI prefix at the top
-    let orig_image_bytes = STANDARD.decode(&clean_base64).map_err(|e| format!("Base64 Error (Orig): {}", e))?;
-    std::fs::write(&original_image_path, orig_image_bytes).map_err(|e| e.to_string())?;
-
-    let gen_image_bytes = STANDARD.decode(&gen_image_b64).map_err(|e| format!("Base64 Error (Gen): {}", e))?;
-    std::fs::write(&generated_image_path, gen_image_bytes).map_err(|e| e.to_string())?;
-
-    // Let frontend know we saved the images so it can show them early
-    #[derive(serde::Serialize, Clone)]
-    struct PartialImages {
-        original_image_path: String,
-        image_path: String,
-    }
-    let _ = app.emit("pipeline-images", PartialImages {
-        original_image_path: original_image_path.to_string_lossy().to_string(),
-        image_path: generated_image_path.to_string_lossy().to_string(),
-    });
-
-    // Step 4-6: Try rig+animation first; if unsupported/failed, fallback to static base model.
-    let rigged_model_paths: Result<(String, String), String> = async {
-        let _ = app.emit("pipeline-progress", "Creating Rigging task...");
-        let rig_task_id = meshy::create_rigging_task(task_id.clone()).await?;
-        meshy::poll_for_rigging_success(&app, rig_task_id.clone()).await?;
-
-        let _ = app.emit("pipeline-progress", "Creating Animation tasks (Idle and Attack)...");
-        let idle_anim_task_id = meshy::create_animation_task(rig_task_id.clone(), 0).await?;
-        let attack_anim_task_id = meshy::create_animation_task(rig_task_id.clone(), 92).await?;
-
-        let (idle_url_res, attack_url_res) = tokio::join!(
-            meshy::poll_for_animation_glb(&app, idle_anim_task_id.clone(), "Idle"),
-            meshy::poll_for_animation_glb(&app, attack_anim_task_id.clone(), "Attack")
-        );
-
-        let idle_url = idle_url_res?;
-        let attack_url = attack_url_res?;
-
-        let idle_filename = format!("{}_idle.glb", task_id);
-        let attack_filename = format!("{}_attack.glb", task_id);
-        let idle_path = meshy::download_glb(app.clone(), idle_url, idle_filename).await?;
-        let attack_path = meshy::download_glb(app.clone(), attack_url, attack_filename).await?;
-        Ok((idle_path, attack_path))
-    }.await;
-
-    let (idle_path, attack_path) = match rigged_model_paths {
-        Ok(paths) => paths,
-        Err(err) => {
-            eprintln!("Rigging/animation pipeline failed for task {}: {}", task_id, err);
-            let _ = app.emit(
-                "pipeline-progress",
-                "Rigging/animation unavailable. Falling back to static model..."
-            );
-
-            let fallback_filename = format!("{}_base.glb", task_id);
-            let fallback_path = meshy::download_glb(app.clone(), base_glb_url, fallback_filename).await?;
-            (fallback_path.clone(), fallback_path)
+#[tauri::command]
+fn get_robot(state: tauri::State<'_, Mutex<rusqlite::Connection>>, id: String) -> AppResult<Option<db::RobotRecord>> {
+    let conn = state.lock()?;
+    db::get_robot(&conn, &id)
+}
+
+#[tauri::command]
+fn update_robot(
+    state: tauri::State<'_, Mutex<rusqlite::Connection>>,
+    id: String,
+    update: db::RobotUpdate,
+) -> AppResult<db::RobotRecord> {
+    if let Some(name) = &update.name {
+        if name.trim().is_empty() {
+            return Err(AppError::invalid_input("Robot name must not be empty"));
         }
+    }
+
+    let conn = state.lock()?;
+    db::update_robot(&conn, &id, &update)?
+        .ok_or_else(|| AppError::not_found(format!("Robot not found: {}", id)))
+}
+
+#[tauri::command]
+fn delete_robot(
+    state: tauri::State<'_, Mutex<rusqlite::Connection>>,
+    assets: tauri::State<'_, assets::AssetStore>,
+    id: String,
+) -> AppResult<()> {
+    let robot = {
+        let conn = state.lock()?;
+        db::delete_robot(&conn, &id)?
+            .ok_or_else(|| AppError::not_found(format!("Robot not found: {}", id)))?
     };
 
-    let elapsed = start_time.elapsed().unwrap_or_default().as_millis() as i64;
-    let created_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
-
-    let new_robot = db::RobotRecord {
-        id: uuid::Uuid::new_v4().to_string(),
-        name: stats.name,
-        lore: stats.lore,
-        hp: stats.hp,
-        atk: stats.atk,
-        def: stats.def,
-        original_image_path: original_image_path.to_string_lossy().to_string(),
-        image_path: generated_image_path.to_string_lossy().to_string(),
-        model_path: idle_path,
-        attack_model_path: attack_path,
-        created_at,
-        generation_time_ms: elapsed,
+    for path in [
+        &robot.original_image_path,
+        &robot.image_path,
+        &robot.model_path,
+        &robot.attack_model_path,
+    ] {
+        assets.remove_legacy(path);
+    }
+    assets.remove_robot(&robot.id);
+
+    Ok(())
+}
+
+/// Report robots with missing files and orphaned files, cleaning up the
+/// latter according to `action` (report only by default).
+#[tauri::command]
+fn run_maintenance(
+    state: tauri::State<'_, Mutex<rusqlite::Connection>>,
+    assets: tauri::State<'_, assets::AssetStore>,
+    action: Option<maintenance::OrphanAction>,
+) -> AppResult<maintenance::MaintenanceReport> {
+    let conn = state.lock()?;
+    maintenance::run(&conn, &assets, action.unwrap_or_default())
+}
+
+/// Write the robot as an `.ffr` package to `destination`, or to the
+/// downloads folder when none is given. Returns the written path.
+#[tauri::command]
+fn export_robot(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<rusqlite::Connection>>,
+    assets: tauri::State<'_, assets::AssetStore>,
+    id: String,
+    destination: Option<String>,
+) -> AppResult<String> {
+    let robot = {
+        let conn = state.lock()?;
+        db::get_robot(&conn, &id)?.ok_or_else(|| AppError::not_found(format!("Robot not found: {}", id)))?
     };
+    let destination = match destination {
+        Some(path) => std::path::PathBuf::from(path),
+        None => app.path().download_dir()?.join(package::file_name(&robot)),
+    };
+    package::export(&robot, &assets, &destination)?;
+    Ok(destination.to_string_lossy().to_string())
+}
 
-    let conn = state.lock().map_err(|e| e.to_string())?;
-    db::insert_robot(&conn, &new_robot).map_err(|e| e.to_string())?;
+/// Import the `.ffr` package at `path`, as picked in the open dialog. When
+/// the robot already exists and `on_conflict` is not given, nothing is
+/// imported and the result has status `conflict` so the user can choose.
+#[tauri::command]
+fn import_robot(
+    state: tauri::State<'_, Mutex<rusqlite::Connection>>,
+    assets: tauri::State<'_, assets::AssetStore>,
+    settings: tauri::State<'_, Arc<settings::SettingsStore>>,
+    path: String,
+    on_conflict: Option<package::ConflictPolicy>,
+) -> AppResult<package::ImportResult> {
+    let bytes = package::read_package(std::path::Path::new(&path))?;
+    package::import(state.inner(), &assets, &bytes, &settings.get().stats, on_conflict)
+}
 
-    Ok(new_robot)
+/// Turn an asset ID from a robot or job into a path the frontend can read.
+#[tauri::command]
+fn resolve_asset(assets: tauri::State<'_, assets::AssetStore>, asset_id: String) -> AppResult<String> {
+    Ok(assets.resolve(&asset_id)?.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+fn get_db_status(status: tauri::State<'_, db::DbStatus>) -> db::DbStatus {
+    status.inner().clone()
+}
+
+#[tauri::command]
+async fn check_duplicate(app: tauri::AppHandle, base64_image: String) -> AppResult<Option<pipeline::DuplicateMatch>> {
+    pipeline::check_duplicate(&app, base64_image).await
+}
+
+#[tauri::command]
+async fn run_generation_pipeline(
+    app: tauri::AppHandle,
+    base64_image: String,
+    allow_duplicate: Option<bool>,
+) -> AppResult<db::RobotRecord> {
+    pipeline::start_job(&app, base64_image, allow_duplicate.unwrap_or(false)).await
+}
+
+#[tauri::command]
+fn list_jobs(
+    state: tauri::State<'_, Mutex<rusqlite::Connection>>,
+    limit: Option<u32>,
+) -> AppResult<Vec<db::GenerationJob>> {
+    let conn = state.lock()?;
+    db::list_jobs(&conn, limit.unwrap_or(db::DEFAULT_PAGE_SIZE))
+}
+
+#[tauri::command]
+fn cancel_generation(queue: tauri::State<'_, pipeline::JobQueue>, job_id: String) -> AppResult<()> {
+    if queue.cancel(&job_id) {
+        Ok(())
+    } else {
+        Err(AppError::not_found(format!("No running generation job: {}", job_id)))
+    }
+}
+
+#[tauri::command]
+fn get_api_key_status(keys: tauri::State<'_, Arc<keys::ApiKeyStore>>) -> AppResult<Vec<keys::ApiKeyStatus>> {
+    keys::KEYED_PROVIDERS.iter().map(|p| keys.status(*p)).collect()
+}
+
+#[tauri::command]
+fn set_api_key(
+    keys: tauri::State<'_, Arc<keys::ApiKeyStore>>,
+    provider: error::Provider,
+    api_key: String,
+) -> AppResult<keys::ApiKeyStatus> {
+    keys.set(provider, &api_key)?;
+    keys.status(provider)
+}
+
+#[tauri::command]
+fn clear_api_key(
+    keys: tauri::State<'_, Arc<keys::ApiKeyStore>>,
+    provider: error::Provider,
+) -> AppResult<keys::ApiKeyStatus> {
+    keys.clear(provider)?;
+    keys.status(provider)
+}
+
+/// Check a key before saving it: format first, then one live request with it.
+#[tauri::command]
+async fn validate_api_key(
+    client: tauri::State<'_, reqwest::Client>,
+    keys: tauri::State<'_, Arc<keys::ApiKeyStore>>,
+    settings: tauri::State<'_, Arc<settings::SettingsStore>>,
+    provider: error::Provider,
+    api_key: String,
+) -> AppResult<()> {
+    let api_key = api_key.trim();
+    keys::validate_format(provider, api_key)?;
+    keys::check_connection(&client, &keys, &settings, provider, Some(api_key)).await
+}
+
+#[tauri::command]
+async fn test_connections(
+    client: tauri::State<'_, reqwest::Client>,
+    keys: tauri::State<'_, Arc<keys::ApiKeyStore>>,
+    settings: tauri::State<'_, Arc<settings::SettingsStore>>,
+) -> AppResult<Vec<keys::ConnectionCheck>> {
+    Ok(keys::test_connections(&client, &keys, &settings).await)
+}
+
+#[tauri::command]
+fn get_settings(settings: tauri::State<'_, Arc<settings::SettingsStore>>) -> settings::Settings {
+    settings.get()
+}
+
+/// Replace the settings. Omitted fields take their defaults; invalid values
+/// are rejected without touching the saved file.
+#[tauri::command]
+fn update_settings(
+    store: tauri::State<'_, Arc<settings::SettingsStore>>,
+    settings: settings::Settings,
+) -> AppResult<settings::Settings> {
+    store.update(settings)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -159,6 +295,7 @@ pub fn run() {
     let _ = dotenvy::dotenv(); // Load .env file
 
     tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
@@ -166,8 +303,60 @@ pub fn run() {
             std::fs::create_dir_all(&app_data_dir).expect("Failed to create app_data_dir");
             let db_path = app_data_dir.join("robots.db");
             
-            let conn = db::init_db(&db_path).expect("Failed to init database");
+            let (conn, db_status) = db::init_db(&db_path).expect("Failed to init database");
+            if let Some(err) = &db_status.migration_error {
+                eprintln!(
+                    "Database migration to v{} failed (schema at v{}): {}",
+                    err.failed_version, db_status.schema_version, err.message
+                );
+            }
+            let asset_store = assets::AssetStore::open(&app_data_dir)?;
+            match assets::migrate_legacy_paths(&conn, &asset_store) {
+                Ok(0) => {}
+                Ok(n) => println!("Moved the files of {} robots into the asset store", n),
+                Err(e) => eprintln!("Failed to move robot files into the asset store: {}", e),
+            }
+            // Startup only reports orphans; moving or deleting them is left to the user.
+            match maintenance::run(&conn, &asset_store, maintenance::OrphanAction::Report) {
+                Ok(report) => {
+                    if !report.missing.is_empty() {
+                        eprintln!("{} robot files are missing", report.missing.len());
+                    }
+                    if !report.orphans.is_empty() {
+                        println!(
+                            "Found {} orphaned files ({} bytes); clean them up from the settings page",
+                            report.orphans.len(),
+                            report.usage.orphan_bytes
+                        );
+                    }
+                }
+                Err(e) => eprintln!("Asset integrity check failed: {}", e),
+            }
+            app.manage(asset_store);
             app.manage(Mutex::new(conn));
+            app.manage(db_status);
+            app.manage(pipeline::JobQueue::from_env());
+            let http_config = http::HttpConfig::from_env();
+            let http_client = match http::build_client(&http_config) {
+                Ok(client) => client,
+                // A bad proxy setting shouldn't keep the app from starting.
+                Err(e) => {
+                    eprintln!("{}; connecting without FFR_HTTP_PROXY", e);
+                    http::build_client(&http::HttpConfig { proxy: None, ..http_config })?
+                }
+            };
+            let api_keys = Arc::new(keys::ApiKeyStore::open(&app_data_dir));
+            let settings = Arc::new(settings::SettingsStore::open(&app_data_dir));
+            app.manage(providers::Providers::from_env(
+                http_client.clone(),
+                api_keys.clone(),
+                settings.clone(),
+            ));
+            app.manage(http_client);
+            app.manage(api_keys);
+            app.manage(settings);
+
+            tauri::async_runtime::spawn(pipeline::resume_unfinished_jobs(app.handle().clone()));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -176,7 +365,27 @@ pub fn run() {
             test_imagen_generate,
             test_meshy_generate,
             get_all_robots,
-            run_generation_pipeline
+            query_robots,
+            search_robots,
+            get_robot,
+            update_robot,
+            delete_robot,
+            resolve_asset,
+            run_maintenance,
+            export_robot,
+            import_robot,
+            get_db_status,
+            check_duplicate,
+            run_generation_pipeline,
+            list_jobs,
+            cancel_generation,
+            get_api_key_status,
+            set_api_key,
+            clear_api_key,
+            validate_api_key,
+            test_connections,
+            get_settings,
+            update_settings
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,331 @@
+//! Integrity check for the asset store: finds robots whose files are gone,
+//! cleans up files no robot or job refers to (left behind by failed or
+//! interrupted pipelines, or by the old flat layout), and totals disk usage.
+//! Startup only reports; files are moved or deleted only when the user asks
+//! via the `run_maintenance` command.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::assets::AssetStore;
+use crate::clock;
+use crate::db;
+use crate::error::AppResult;
+
+/// Files younger than this are never treated as orphans, so a job that has
+/// written its first asset but not yet its database row is left alone.
+const ORPHAN_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanAction {
+    /// Only list orphans.
+    #[default]
+    Report,
+    /// Move orphans into `quarantine/<timestamp>/` so they can be recovered by hand.
+    Quarantine,
+    Delete,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MissingAsset {
+    pub robot_id: String,
+    pub robot_name: String,
+    /// Robot field that refers to the file, e.g. `model_path`.
+    pub field: &'static str,
+    pub asset_id: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DiskUsage {
+    /// Files referenced by robots or unfinished jobs.
+    pub asset_bytes: u64,
+    /// Orphans found by this run, before they were moved or deleted.
+    pub orphan_bytes: u64,
+    pub quarantine_bytes: u64,
+    pub database_bytes: u64,
+    /// Everything in the app data dir after the cleanup.
+    pub total_bytes: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MaintenanceReport {
+    pub missing: Vec<MissingAsset>,
+    /// Orphan paths relative to the app data dir.
+    pub orphans: Vec<String>,
+    pub action: OrphanAction,
+    pub usage: DiskUsage,
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() { dir_size(&path) } else { file_size(&path) }
+        })
+        .sum()
+}
+
+fn is_recent(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < ORPHAN_GRACE_PERIOD)
+}
+
+/// Orphans inside the asset store, plus flat-layout files directly in the app
+/// data dir that the database still names. Nothing else in the data dir is
+/// touched, since the app can't tell what put it there.
+fn find_orphans(
+    store: &AssetStore,
+    referenced: &HashSet<PathBuf>,
+    legacy: &HashSet<PathBuf>,
+    active_jobs: &HashSet<String>,
+) -> Vec<PathBuf> {
+    let mut orphans: Vec<PathBuf> = legacy
+        .iter()
+        .filter(|path| path.is_file() && !referenced.contains(*path) && !is_recent(path))
+        .cloned()
+        .collect();
+
+    if let Ok(folders) = fs::read_dir(store.root()) {
+        for folder in folders.flatten().map(|e| e.path()) {
+            let owner = folder.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            // A running job may still add files to its folder.
+            if !folder.is_dir() || active_jobs.contains(owner) {
+                continue;
+            }
+            let Ok(files) = fs::read_dir(&folder) else {
+                continue;
+            };
+            for path in files.flatten().map(|e| e.path()) {
+                if !referenced.contains(&path) && !is_recent(&path) {
+                    orphans.push(path);
+                }
+            }
+        }
+    }
+
+    orphans.sort();
+    orphans
+}
+
+fn dispose(store: &AssetStore, orphans: &[PathBuf], action: OrphanAction) {
+    if action == OrphanAction::Report {
+        return;
+    }
+    let quarantine = store.quarantine_dir().join((clock::now_ms() / 1000).to_string());
+    for path in orphans {
+        let result = if action == OrphanAction::Delete {
+            fs::remove_file(path)
+        } else {
+            let relative = path.strip_prefix(store.data_dir()).unwrap_or(path);
+            let target = quarantine.join(relative);
+            target
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| fs::rename(path, &target))
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to clean up orphan {}: {}", path.display(), e);
+        }
+    }
+
+    // Drop robot folders emptied by the cleanup.
+    if let Ok(folders) = fs::read_dir(store.root()) {
+        for folder in folders.flatten().map(|e| e.path()) {
+            if fs::read_dir(&folder).is_ok_and(|mut files| files.next().is_none()) {
+                let _ = fs::remove_dir(&folder);
+            }
+        }
+    }
+}
+
+/// Check every robot's files, handle orphans according to `action`, and total disk usage.
+pub fn run(conn: &Connection, store: &AssetStore, action: OrphanAction) -> AppResult<MaintenanceReport> {
+    let mut missing = Vec::new();
+    let mut referenced = HashSet::new();
+    let mut legacy = HashSet::new();
+    let mut record_legacy = |asset_id: &str| {
+        let path = Path::new(asset_id);
+        if path.parent() == Some(store.data_dir()) {
+            legacy.insert(path.to_path_buf());
+        }
+    };
+
+    for robot in db::get_robots(conn)? {
+        for (field, asset_id) in [
+            ("original_image_path", &robot.original_image_path),
+            ("image_path", &robot.image_path),
+            ("model_path", &robot.model_path),
+            ("attack_model_path", &robot.attack_model_path),
+        ] {
+            record_legacy(asset_id);
+            match store.resolve(asset_id) {
+                Ok(path) => {
+                    referenced.insert(path);
+                }
+                Err(_) => missing.push(MissingAsset {
+                    robot_id: robot.id.clone(),
+                    robot_name: robot.name.clone(),
+                    field,
+                    asset_id: asset_id.clone(),
+                }),
+            }
+        }
+    }
+
+    // Finished jobs may name flat-layout files too, e.g. the upload of a failed job.
+    for job in db::list_jobs(conn, u32::MAX)? {
+        for asset_id in std::iter::once(&job.original_image_path).chain(&job.generated_image_path) {
+            record_legacy(asset_id);
+        }
+    }
+
+    let mut active_jobs = HashSet::new();
+    for job in db::get_unfinished_jobs(conn)? {
+        for asset_id in std::iter::once(&job.original_image_path).chain(&job.generated_image_path) {
+            if let Ok(path) = store.resolve(asset_id) {
+                referenced.insert(path);
+            }
+        }
+        active_jobs.insert(job.id);
+    }
+
+    let orphans = find_orphans(store, &referenced, &legacy, &active_jobs);
+    let orphan_bytes: u64 = orphans.iter().map(|p| file_size(p)).sum();
+    dispose(store, &orphans, action);
+
+    let usage = DiskUsage {
+        asset_bytes: referenced.iter().map(|p| file_size(p)).sum(),
+        orphan_bytes,
+        quarantine_bytes: dir_size(&store.quarantine_dir()),
+        database_bytes: ["robots.db", "robots.db-wal", "robots.db-shm"]
+            .iter()
+            .map(|name| file_size(&store.data_dir().join(name)))
+            .sum(),
+        total_bytes: dir_size(store.data_dir()),
+    };
+
+    Ok(MaintenanceReport {
+        missing,
+        orphans: orphans
+            .iter()
+            .map(|p| p.strip_prefix(store.data_dir()).unwrap_or(p).to_string_lossy().to_string())
+            .collect(),
+        action,
+        usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestInstall;
+
+    /// Backdate `path` past the grace period.
+    fn make_old(path: &Path) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - 2 * ORPHAN_GRACE_PERIOD)
+            .unwrap();
+    }
+
+    fn put_old(store: &AssetStore, owner: &str, bytes: &[u8], extension: &str) -> String {
+        let id = store.put(owner, bytes, extension).unwrap();
+        make_old(&store.resolve(&id).unwrap());
+        id
+    }
+
+    #[test]
+    fn keeps_referenced_files() {
+        let install = TestInstall::new();
+        let (store, conn) = (&install.store, install.conn());
+        let robot = db::RobotRecord {
+            id: "robot".to_string(),
+            name: "Ramen Titan".to_string(),
+            original_image_path: put_old(store, "robot", b"photo", "jpg"),
+            image_path: put_old(store, "robot", b"concept", "png"),
+            model_path: put_old(store, "robot", b"idle", "glb"),
+            attack_model_path: put_old(store, "robot", b"attack", "glb"),
+            ..Default::default()
+        };
+        db::insert_robot(&conn, &robot).unwrap();
+
+        let report = run(&conn, store, OrphanAction::Quarantine).unwrap();
+        assert!(report.orphans.is_empty());
+        assert!(report.missing.is_empty());
+        assert!(store.resolve(&robot.model_path).unwrap().is_file());
+    }
+
+    #[test]
+    fn keeps_files_of_active_jobs() {
+        let install = TestInstall::new();
+        let (store, conn) = (&install.store, install.conn());
+        let original = put_old(store, "job", b"photo", "jpg");
+        // Not referenced by the job row yet, e.g. a model still being written.
+        let partial = put_old(store, "job", b"partial", "glb");
+        let job = db::GenerationJob {
+            id: "job".to_string(),
+            status: db::JobStatus::Running,
+            original_image_path: original.clone(),
+            image_hash: None,
+            perceptual_hash: None,
+            stats_json: None,
+            generated_image_path: None,
+            image_task_id: None,
+            rig_task_id: None,
+            idle_anim_task_id: None,
+            attack_anim_task_id: None,
+            rigging_error: None,
+            robot_id: None,
+            error: None,
+            created_at_ms: 0,
+            updated_at_ms: 0,
+        };
+        db::insert_job(&conn, &job).unwrap();
+
+        let report = run(&conn, store, OrphanAction::Quarantine).unwrap();
+        assert!(report.orphans.is_empty());
+        assert!(store.resolve(&original).is_ok());
+        assert!(store.resolve(&partial).is_ok());
+    }
+
+    #[test]
+    fn reports_then_quarantines_old_unreferenced_files() {
+        let install = TestInstall::new();
+        let (store, conn) = (&install.store, install.conn());
+        let stale = put_old(store, "gone", b"stale", "glb");
+        let fresh = store.put("gone", b"fresh", "glb").unwrap();
+        // Not named by the database, so not ours to clean up.
+        let stray = install.dir.path().join("dev_output.png");
+        fs::write(&stray, b"png").unwrap();
+        make_old(&stray);
+
+        let report = run(&conn, store, OrphanAction::Report).unwrap();
+        assert_eq!(report.orphans, [format!("assets/{}", stale)]);
+        assert!(store.resolve(&stale).is_ok());
+
+        let report = run(&conn, store, OrphanAction::Quarantine).unwrap();
+        assert_eq!(report.orphans.len(), 1);
+        assert!(store.resolve(&stale).is_err());
+        assert!(store.resolve(&fresh).is_ok());
+        assert!(stray.is_file());
+        assert!(report.usage.quarantine_bytes > 0);
+    }
+}
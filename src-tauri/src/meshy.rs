@@ -1,11 +1,112 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
-use std::fs;
-use std::io::Write;
+use std::sync::Arc;
 use std::time::Duration;
-use tauri::{AppHandle, Manager, Emitter};
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
+
+use crate::error::{AppError, AppResult, ErrorCode, Provider};
+use crate::keys::ApiKeyStore;
+use crate::settings::{PollingSettings, SettingsStore};
+use crate::progress::{PipelineStage, ProgressReporter};
+use crate::providers::{MeshProvider, MeshTaskKind};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.meshy.ai/openapi/v1";
+
+/// Meshy-backed `MeshProvider`.
+pub struct MeshyProvider {
+    /// Root of the Meshy OpenAPI, up to and including the version segment.
+    base_url: String,
+    client: Client,
+    keys: Arc<ApiKeyStore>,
+    settings: Arc<SettingsStore>,
+}
+
+impl MeshyProvider {
+    pub fn new(
+        client: Client,
+        keys: Arc<ApiKeyStore>,
+        settings: Arc<SettingsStore>,
+        base_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client,
+            keys,
+            settings,
+        }
+    }
+
+    /// `MESHY_API_BASE_URL` points task creation, polling and the balance check
+    /// at another host, e.g. a local server that replays recorded Meshy tasks.
+    pub fn from_env(client: Client, keys: Arc<ApiKeyStore>, settings: Arc<SettingsStore>) -> Self {
+        Self::new(client, keys, settings, env::var("MESHY_API_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()))
+    }
+}
+
+impl MeshyProvider {
+    /// Verify a key by reading the account balance, which spends no credits.
+    /// Tests `api_key` when given, else the stored Meshy key.
+    pub async fn check_connection(&self, api_key: Option<&str>) -> AppResult<()> {
+        let api_key = match api_key {
+            Some(key) => key.to_string(),
+            None => self.keys.get(Provider::Meshy)?,
+        };
+        check_connection(&self.client, &self.base_url, &api_key).await
+    }
+
+    fn api_key(&self) -> AppResult<String> {
+        self.keys.get(Provider::Meshy)
+    }
+
+    fn poll_policy(&self, kind: MeshTaskKind) -> PollPolicy {
+        PollPolicy::from_settings(kind, &self.settings.get().meshy.polling)
+    }
+}
+
+#[async_trait::async_trait]
+impl MeshProvider for MeshyProvider {
+    async fn create_mesh_task(&self, base64_image: String, mime_type: &str) -> AppResult<String> {
+        let enable_pbr = self.settings.get().meshy.enable_pbr;
+        create_image_to_3d_task(&self.client, &self.base_url, &self.api_key()?, enable_pbr, base64_image, mime_type).await
+    }
+
+    async fn wait_for_mesh(&self, progress: &ProgressReporter, task_id: &str) -> AppResult<String> {
+        let policy = self.poll_policy(MeshTaskKind::ImageTo3d);
+        poll_for_glb_url(&self.client, &self.base_url, &self.api_key()?, policy, progress, task_id).await
+    }
+
+    async fn create_rig_task(&self, mesh_task_id: &str) -> AppResult<String> {
+        create_rigging_task(&self.client, &self.base_url, &self.api_key()?, mesh_task_id.to_string()).await
+    }
+
+    async fn wait_for_rig(&self, progress: &ProgressReporter, rig_task_id: &str) -> AppResult<()> {
+        let policy = self.poll_policy(MeshTaskKind::Rigging);
+        poll_for_rigging_success(&self.client, &self.base_url, &self.api_key()?, policy, progress, rig_task_id).await
+    }
+
+    async fn create_animation_task(&self, rig_task_id: &str, action_id: u32) -> AppResult<String> {
+        create_animation_task(&self.client, &self.base_url, &self.api_key()?, rig_task_id.to_string(), action_id).await
+    }
+
+    async fn wait_for_animation(
+        &self,
+        progress: &ProgressReporter,
+        task_id: &str,
+        name: &str,
+    ) -> AppResult<String> {
+        let policy = self.poll_policy(MeshTaskKind::Animation);
+        poll_for_animation_glb(&self.client, &self.base_url, &self.api_key()?, policy, progress, task_id, name).await
+    }
+
+    async fn cancel_task(&self, kind: MeshTaskKind, task_id: &str) -> AppResult<()> {
+        delete_task(&self.client, &self.base_url, &self.api_key()?, kind, task_id).await
+    }
+
+    async fn download_model(&self, url: &str) -> AppResult<Vec<u8>> {
+        download_glb(&self.client, url).await
+    }
+}
 
 #[derive(Serialize)]
 struct CreateTaskRequest {
@@ -18,12 +119,15 @@ struct CreateTaskResponse {
     result: String, // This is the task_id
 }
 
+/// Status payload shared by every Meshy task type. Image-to-3D tasks report
+/// `model_urls`; animation tasks report `result`.
 #[derive(Deserialize, Debug)]
 pub struct TaskStatusResponse {
-    pub id: String,
-    pub status: String, // "PENDING", "IN_PROGRESS", "SUCCEEDED", "FAILED"
-    pub progress: u32,
+    pub status: String, // "PENDING", "IN_PROGRESS", "SUCCEEDED", "FAILED", "CANCELED"
+    #[serde(default)]
+    pub progress: Option<u32>,
     pub model_urls: Option<ModelUrls>,
+    pub result: Option<AnimationUrls>,
     pub task_error: Option<TaskError>,
 }
 
@@ -34,220 +138,315 @@ pub struct ModelUrls {
     pub obj: Option<String>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct AnimationUrls {
+    pub animation_glb_url: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct TaskError {
     pub message: String,
 }
 
-/// Start an Image-to-3D task
-pub async fn create_image_to_3d_task(base64_image: String) -> Result<String, String> {
-    let api_key = env::var("MESHY_AI_API_KEY")
-        .map_err(|_| "MESHY_AI_API_KEY not found".to_string())?;
+fn timeout(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorCode::Timeout, message).with_provider(Provider::Meshy)
+}
+
+fn task_failed(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorCode::TaskFailed, message).with_provider(Provider::Meshy)
+}
 
-    let url = "https://api.meshy.ai/openapi/v1/image-to-3d";
-    let data_uri = format!("data:image/png;base64,{}", base64_image);
+/// API path segment for a task family.
+fn task_endpoint(kind: MeshTaskKind) -> &'static str {
+    match kind {
+        MeshTaskKind::ImageTo3d => "image-to-3d",
+        MeshTaskKind::Rigging => "rigging",
+        MeshTaskKind::Animation => "animations",
+    }
+}
+
+fn task_label(kind: MeshTaskKind) -> &'static str {
+    match kind {
+        MeshTaskKind::ImageTo3d => "Image-to-3D",
+        MeshTaskKind::Rigging => "Rigging",
+        MeshTaskKind::Animation => "Animation",
+    }
+}
+
+/// Fetch the credit balance, which needs a valid key but costs nothing.
+pub async fn check_connection(client: &Client, base_url: &str, api_key: &str) -> AppResult<()> {
+    let url = format!("{}/balance", base_url);
+    let res = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to reach Meshy", e))?;
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::from_http(Provider::Meshy, status, &text));
+    }
+    Ok(())
+}
+
+/// Start an Image-to-3D task
+pub async fn create_image_to_3d_task(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    enable_pbr: bool,
+    base64_image: String,
+    mime_type: &str,
+) -> AppResult<String> {
+    let url = format!("{}/image-to-3d", base_url);
+    let data_uri = format!("data:{};base64,{}", mime_type, base64_image);
 
     let request_body = CreateTaskRequest {
         image_url: data_uri,
-        enable_pbr: true,
+        enable_pbr,
     };
 
-    let client = Client::new();
     let res = client
-        .post(url)
+        .post(&url)
         .header("Authorization", format!("Bearer {}", api_key))
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Failed to send Meshy request: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to send Meshy request", e))?;
 
     if !res.status().is_success() {
+        let status = res.status();
         let error_text = res.text().await.unwrap_or_default();
-        return Err(format!("Meshy API Error: {}", error_text));
+        return Err(AppError::from_http(Provider::Meshy, status, &error_text));
     }
 
     let response_data: CreateTaskResponse = res
         .json()
         .await
-        .map_err(|e| format!("Failed to parse Meshy response: {}", e))?;
+        .map_err(|e| AppError::invalid_response(Provider::Meshy, format!("Failed to parse Meshy response: {}", e)))?;
 
     Ok(response_data.result)
 }
 
-/// Check the status of a task
-pub async fn get_task_status(task_id: &str) -> Result<TaskStatusResponse, String> {
-    let api_key = env::var("MESHY_AI_API_KEY")
-        .map_err(|_| "MESHY_AI_API_KEY not found".to_string())?;
+// --- Task polling ---
+
+/// How often and for how long to poll a Meshy task.
+#[derive(Debug, Clone, Copy)]
+pub struct PollPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Factor applied to the delay after every poll.
+    pub multiplier: f64,
+    /// Give up once the task has been polled for this long.
+    pub deadline: Duration,
+}
 
-    let url = format!("https://api.meshy.ai/openapi/v1/image-to-3d/{}", task_id);
+impl PollPolicy {
+    pub fn from_settings(kind: MeshTaskKind, polling: &PollingSettings) -> Self {
+        let deadline_secs = match kind {
+            MeshTaskKind::ImageTo3d => polling.mesh_timeout_secs,
+            MeshTaskKind::Rigging => polling.rigging_timeout_secs,
+            MeshTaskKind::Animation => polling.animation_timeout_secs,
+        };
+        Self {
+            initial_delay: Duration::from_millis(polling.initial_delay_ms),
+            max_delay: Duration::from_millis(polling.max_delay_ms),
+            multiplier: polling.backoff_multiplier,
+            deadline: Duration::from_secs(deadline_secs),
+        }
+    }
 
-    let client = Client::new();
+    fn next_delay(&self, current: Duration) -> Duration {
+        current.mul_f64(self.multiplier).min(self.max_delay)
+    }
+}
+
+/// Randomize the upper half of `delay` so concurrent jobs don't poll in lockstep.
+fn with_jitter(delay: Duration) -> Duration {
+    let half = delay / 2;
+    half + half.mul_f64(fastrand::f64())
+}
+
+/// `Retry-After` in its delay-seconds form. The HTTP-date form is ignored.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// A failed status request, with the wait the server asked for, if any.
+struct PollError {
+    error: AppError,
+    retry_after: Option<Duration>,
+}
+
+impl From<AppError> for PollError {
+    fn from(error: AppError) -> Self {
+        Self { error, retry_after: None }
+    }
+}
+
+async fn fetch_task_status(
+    client: &Client,
+    api_key: &str,
+    url: &str,
+) -> Result<TaskStatusResponse, PollError> {
     let res = client
-        .get(&url)
+        .get(url)
         .header("Authorization", format!("Bearer {}", api_key))
         .send()
         .await
-        .map_err(|e| format!("Failed to send task status request: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to send task status request", e))?;
 
     if !res.status().is_success() {
-        let error_text = res.text().await.unwrap_or_default();
-        return Err(format!("Meshy API Error: {}", error_text));
+        let status = res.status();
+        let retry_after = retry_after(res.headers());
+        let text = res.text().await.unwrap_or_default();
+        return Err(PollError {
+            error: AppError::from_http(Provider::Meshy, status, &text),
+            retry_after,
+        });
     }
 
-    let response_data: TaskStatusResponse = res
+    Ok(res
         .json()
         .await
-        .map_err(|e| format!("Failed to parse task status response: {}", e))?;
-
-    Ok(response_data)
+        .map_err(|e| AppError::invalid_response(Provider::Meshy, format!("Failed to parse task status: {}", e)))?)
 }
 
-/// Helper function to poll until completed and return GLB URL
-pub async fn poll_for_glb_url(app: &AppHandle, task_id: String) -> Result<String, String> {
-    let mut attempts = 0;
-    let max_attempts = 120; // 120 * 5s = 600s (10 minutes)
+/// Poll a task until it succeeds, fails or `policy.deadline` passes, reporting
+/// progress through `on_progress`. Retryable errors (network, 429, 5xx) are
+/// retried with backoff; a `Retry-After` header lengthens the wait. Any other
+/// error aborts.
+pub async fn poll_task(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    kind: MeshTaskKind,
+    task_id: &str,
+    policy: PollPolicy,
+    on_progress: impl Fn(u32),
+) -> AppResult<TaskStatusResponse> {
+    let url = format!("{}/{}/{}", base_url, task_endpoint(kind), task_id);
+    let label = task_label(kind);
+    let deadline = Instant::now() + policy.deadline;
+    let mut delay = policy.initial_delay;
 
     loop {
-        if attempts > max_attempts {
-            return Err("Timeout waiting for Meshy AI task".to_string());
-        }
-
-        let status_res = get_task_status(&task_id).await;
-        
-        match status_res {
-            Ok(status) => {
-                match status.status.as_str() {
-                    "SUCCEEDED" => {
-                        if let Some(urls) = status.model_urls {
-                            if let Some(glb) = urls.glb {
-                                return Ok(glb);
-                            }
-                        }
-                        return Err("Task succeeded but no GLB URL found in response".to_string());
-                    }
-                    "FAILED" => {
-                        let err_msg = status
-                            .task_error
-                            .map(|e| e.message)
-                            .unwrap_or_else(|| "Unknown error".to_string());
-                        return Err(format!("Meshy Task Failed: {}", err_msg));
-                    }
-                    "PENDING" | "IN_PROGRESS" => {
-                        let _ = app.emit("pipeline-progress", format!("Image to 3D Base Model: {}%", status.progress));
-                        // Wait 5 seconds before next polling
-                        sleep(Duration::from_secs(5)).await;
-                        attempts += 1;
-                    }
-                    other => {
-                        return Err(format!("Unknown status: {}", other));
-                    }
+        let mut wait = with_jitter(delay);
+
+        match fetch_task_status(client, api_key, &url).await {
+            Ok(task) => match task.status.as_str() {
+                "SUCCEEDED" => return Ok(task),
+                "FAILED" | "CANCELED" => {
+                    let err_msg = task
+                        .task_error
+                        .map(|e| e.message)
+                        .unwrap_or_else(|| "No detailed message from Meshy".to_string());
+                    return Err(task_failed(format!(
+                        "{} task failed or canceled. ID: {}. Reason: {}",
+                        label, task_id, err_msg
+                    )));
+                }
+                "PENDING" | "IN_PROGRESS" => on_progress(task.progress.unwrap_or(0)),
+                other => {
+                    return Err(AppError::invalid_response(
+                        Provider::Meshy,
+                        format!("Unknown {} task status: {}", label, other),
+                    ));
+                }
+            },
+            Err(PollError { error, retry_after }) => {
+                if !error.retryable {
+                    return Err(error);
+                }
+                println!("Transient error polling {} task {}: {}", label, task_id, error);
+                if let Some(retry_after) = retry_after {
+                    wait = wait.max(retry_after);
                 }
-            }
-            // If the network request fails temporarily, log it and retry instead of crashing pipeline
-            Err(e) => {
-                println!("Transient error polling Image-to-3D task (attempt {}/{}): {}", attempts, max_attempts, e);
-                sleep(Duration::from_secs(5)).await;
-                attempts += 1;
             }
         }
+
+        if Instant::now() + wait > deadline {
+            return Err(timeout(format!("Timeout waiting for {} task {}", label, task_id)));
+        }
+        sleep(wait).await;
+        delay = policy.next_delay(delay);
     }
 }
 
+/// Poll an Image-to-3D task and return its GLB URL.
+pub async fn poll_for_glb_url(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    policy: PollPolicy,
+    progress: &ProgressReporter,
+    task_id: &str,
+) -> AppResult<String> {
+    let task = poll_task(client, base_url, api_key, MeshTaskKind::ImageTo3d, task_id, policy, |p| {
+        progress.stage_progress(PipelineStage::GeneratingMesh, p)
+    })
+    .await?;
+
+    task.model_urls
+        .and_then(|urls| urls.glb)
+        .ok_or_else(|| AppError::invalid_response(Provider::Meshy, "Task succeeded but no GLB URL found in response"))
+}
+
 // --- Rigging API ---
 #[derive(Serialize)]
 struct CreateRiggingRequest {
     input_task_id: String,
 }
 
-pub async fn create_rigging_task(input_task_id: String) -> Result<String, String> {
-    let api_key = std::env::var("MESHY_AI_API_KEY").map_err(|_| "MESHY_AI_API_KEY not set in .env")?;
-    let client = Client::new();
-    let url = "https://api.meshy.ai/openapi/v1/rigging";
+pub async fn create_rigging_task(client: &Client, base_url: &str, api_key: &str, input_task_id: String) -> AppResult<String> {
+    let url = format!("{}/rigging", base_url);
 
     let request_body = CreateRiggingRequest { input_task_id };
 
     let res = client
-        .post(url)
+        .post(&url)
         .header("Authorization", format!("Bearer {}", api_key))
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Failed to send request to Meshy Rigging API: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to send request to Meshy Rigging API", e))?;
 
     if !res.status().is_success() {
         let status = res.status();
         let text = res.text().await.unwrap_or_default();
-        return Err(format!("Meshy Rigging API error: {} - {}", status, text));
+        return Err(AppError::from_http(Provider::Meshy, status, &text));
     }
 
     let response_data: CreateTaskResponse = res
         .json()
         .await
-        .map_err(|e| format!("Failed to parse Meshy Rigging API response: {}", e))?;
+        .map_err(|e| AppError::invalid_response(Provider::Meshy, format!("Failed to parse Meshy Rigging API response: {}", e)))?;
 
     Ok(response_data.result)
 }
 
-pub async fn poll_for_rigging_success(app: &AppHandle, task_id: String) -> Result<(), String> {
-    let api_key = std::env::var("MESHY_AI_API_KEY").map_err(|_| "MESHY_AI_API_KEY not set in .env")?;
-    let client = Client::new();
-    let url = format!("https://api.meshy.ai/openapi/v1/rigging/{}", task_id);
-    let mut attempts = 0;
-    let max_attempts = 120;
-
-    loop {
-        let res = client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .send()
-            .await;
-
-        match res {
-            Ok(res) => {
-                if !res.status().is_success() {
-                    let status = res.status();
-                    let text = res.text().await.unwrap_or_default();
-                    return Err(format!("Meshy poll error: {} - {}", status, text));
-                }
-
-                let task_status: TaskStatusResponse = res
-                    .json()
-                    .await
-                    .map_err(|e| format!("Failed to parse Rigging task status: {}", e))?;
-
-                match task_status.status.as_str() {
-                    "SUCCEEDED" => {
-                        return Ok(());
-                    }
-                    "FAILED" | "CANCELED" => {
-                        let err_msg = task_status
-                            .task_error
-                            .map(|e| e.message)
-                            .unwrap_or_else(|| "No detailed message from Meshy".to_string());
-                        return Err(format!(
-                            "Rigging task failed or canceled. ID: {}. Reason: {}",
-                            task_id, err_msg
-                        ));
-                    }
-                    _ => {
-                        // PENDING or IN_PROGRESS, continue polling
-                        let _ = app.emit("pipeline-progress", format!("Rigging Model: {}%", task_status.progress));
-                        attempts += 1;
-                    }
-                }
-            }
-            Err(e) => {
-                println!("Transient error polling Rigging task: {}", e);
-                attempts += 1;
-            }
-        }
-
-        if attempts > max_attempts {
-            return Err("Timeout waiting for Rigging task".to_string());
-        }
-
-        sleep(Duration::from_secs(10)).await;
-    }
+pub async fn poll_for_rigging_success(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    policy: PollPolicy,
+    progress: &ProgressReporter,
+    task_id: &str,
+) -> AppResult<()> {
+    poll_task(client, base_url, api_key, MeshTaskKind::Rigging, task_id, policy, |p| {
+        progress.stage_progress(PipelineStage::Rigging, p)
+    })
+    .await?;
+    Ok(())
 }
 
 // --- Animation API ---
@@ -257,147 +456,241 @@ struct CreateAnimationRequest {
     action_id: u32,
 }
 
-pub async fn create_animation_task(rig_task_id: String, action_id: u32) -> Result<String, String> {
-    let api_key = std::env::var("MESHY_AI_API_KEY").map_err(|_| "MESHY_AI_API_KEY not set in .env")?;
-    let client = Client::new();
-    let url = "https://api.meshy.ai/openapi/v1/animations";
+pub async fn create_animation_task(client: &Client, base_url: &str, api_key: &str, rig_task_id: String, action_id: u32) -> AppResult<String> {
+    let url = format!("{}/animations", base_url);
 
     let request_body = CreateAnimationRequest { rig_task_id, action_id };
 
     let res = client
-        .post(url)
+        .post(&url)
         .header("Authorization", format!("Bearer {}", api_key))
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Failed to send request to Meshy Animation API: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to send request to Meshy Animation API", e))?;
 
     if !res.status().is_success() {
         let status = res.status();
         let text = res.text().await.unwrap_or_default();
-        return Err(format!("Meshy Animation API error: {} - {}", status, text));
+        return Err(AppError::from_http(Provider::Meshy, status, &text));
     }
 
     let response_data: CreateTaskResponse = res
         .json()
         .await
-        .map_err(|e| format!("Failed to parse Meshy Animation API response: {}", e))?;
+        .map_err(|e| AppError::invalid_response(Provider::Meshy, format!("Failed to parse Meshy Animation API response: {}", e)))?;
 
     Ok(response_data.result)
 }
 
-#[derive(Deserialize, Debug)]
-pub struct AnimationUrls {
-    pub animation_glb_url: Option<String>,
-}
-
-#[derive(Deserialize, Debug)]
-pub struct AnimationTaskStatusResponse {
-    pub status: String,
-    pub progress: Option<u32>,
-    pub result: Option<AnimationUrls>,
-    pub task_error: Option<TaskError>,
+pub async fn poll_for_animation_glb(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    policy: PollPolicy,
+    progress: &ProgressReporter,
+    task_id: &str,
+    anim_name: &str,
+) -> AppResult<String> {
+    let task = poll_task(client, base_url, api_key, MeshTaskKind::Animation, task_id, policy, |p| {
+        progress.subtask_progress(PipelineStage::Animating, anim_name, p)
+    })
+    .await?;
+
+    task.result
+        .ok_or_else(|| AppError::invalid_response(Provider::Meshy, "Task succeeded but result object is missing"))?
+        .animation_glb_url
+        .ok_or_else(|| AppError::invalid_response(Provider::Meshy, "Task succeeded but animation_glb_url is missing"))
 }
 
-pub async fn poll_for_animation_glb(app: &AppHandle, task_id: String, anim_name: &str) -> Result<String, String> {
-    let api_key = std::env::var("MESHY_AI_API_KEY").map_err(|_| "MESHY_AI_API_KEY not set in .env")?;
-    let client = Client::new();
-    let url = format!("https://api.meshy.ai/openapi/v1/animations/{}", task_id);
-    let mut attempts = 0;
-    let max_attempts = 120;
-
-    loop {
-        let res = client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .send()
-            .await;
-
-        match res {
-            Ok(res) => {
-                if !res.status().is_success() {
-                    let status = res.status();
-                    let text = res.text().await.unwrap_or_default();
-                    return Err(format!("Meshy poll error: {} - {}", status, text));
-                }
+/// Best-effort remote cancellation. Meshy has no dedicated cancel endpoint, but
+/// deleting a task stops any further processing of it.
+pub async fn delete_task(client: &Client, base_url: &str, api_key: &str, kind: MeshTaskKind, task_id: &str) -> AppResult<()> {
+    let url = format!("{}/{}/{}", base_url, task_endpoint(kind), task_id);
 
-                let task_status: AnimationTaskStatusResponse = res
-                    .json()
-                    .await
-                    .map_err(|e| format!("Failed to parse Animation task status: {}", e))?;
-
-                match task_status.status.as_str() {
-                    "SUCCEEDED" => {
-                        if let Some(result) = task_status.result {
-                            if let Some(glb_url) = result.animation_glb_url {
-                                return Ok(glb_url);
-                            } else {
-                                return Err("Task succeeded but animation_glb_url is missing".to_string());
-                            }
-                        } else {
-                            return Err("Task succeeded but result object is missing".to_string());
-                        }
-                    }
-                    "FAILED" | "CANCELED" => {
-                        let err_msg = task_status
-                            .task_error
-                            .map(|e| e.message)
-                            .unwrap_or_else(|| "No detailed message from Meshy".to_string());
-                        return Err(format!(
-                            "Animation task failed or canceled. ID: {}. Reason: {}",
-                            task_id, err_msg
-                        ));
-                    }
-                    _ => {
-                        let _ = app.emit("pipeline-progress", format!("Applying Animation ({}): {}%", anim_name, task_status.progress.unwrap_or(0)));
-                        attempts += 1;
-                    }
-                }
-            }
-            Err(e) => {
-                println!("Transient error polling Animation task: {}", e);
-                attempts += 1;
-            }
-        }
-
-        if attempts > max_attempts {
-            return Err(format!("Timeout waiting for Animation task ({})", anim_name));
-        }
+    let res = client
+        .delete(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to send Meshy delete request", e))?;
 
-        sleep(Duration::from_secs(10)).await;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::from_http(Provider::Meshy, status, &text));
     }
+
+    Ok(())
 }
-pub async fn download_glb(app: AppHandle, url: String, filename: String) -> Result<String, String> {
-    let client = Client::new();
+
+pub async fn download_glb(client: &Client, url: &str) -> AppResult<Vec<u8>> {
     let res = client
-        .get(&url)
+        .get(url)
         .send()
         .await
-        .map_err(|e| format!("Failed to request GLB: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to request GLB", e))?;
 
     if !res.status().is_success() {
-        return Err(format!("Failed to download GLB: {}", res.status()));
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::from_http(Provider::Meshy, status, &text));
     }
 
     let bytes = res
         .bytes()
         .await
-        .map_err(|e| format!("Failed to read bytes: {}", e))?;
+        .map_err(|e| AppError::from_reqwest(Provider::Meshy, "Failed to read bytes", e))?;
 
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Could not get app data dir: {}", e))?;
+    Ok(bytes.to_vec())
+}
 
-    fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create AppData directory: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn policy(deadline: Duration) -> PollPolicy {
+        PollPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(20),
+            multiplier: 2.0,
+            deadline,
+        }
+    }
 
-    let file_path = app_data_dir.join(&filename);
-    let mut file = fs::File::create(&file_path)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
-    
-    file.write_all(&bytes)
-        .map_err(|e| format!("Failed to write to file: {}", e))?;
+    #[test]
+    fn backoff_grows_until_max_delay() {
+        let policy = PollPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            deadline: Duration::from_secs(60),
+        };
+        let mut delay = policy.initial_delay;
+        let mut delays = Vec::new();
+        for _ in 0..4 {
+            delay = policy.next_delay(delay);
+            delays.push(delay.as_secs());
+        }
+        assert_eq!(delays, [2, 4, 5, 5]);
+    }
 
-    Ok(file_path.to_string_lossy().to_string())
+    #[test]
+    fn jitter_stays_in_the_upper_half() {
+        let delay = Duration::from_millis(100);
+        for _ in 0..1000 {
+            let jittered = with_jitter(delay);
+            assert!(jittered >= delay / 2 && jittered <= delay, "{:?}", jittered);
+        }
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_only() {
+        let parse = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+            retry_after(&headers)
+        };
+        assert_eq!(parse("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse(" 3 "), Some(Duration::from_secs(3)));
+        assert_eq!(parse("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse("soon"), None);
+        assert_eq!(parse("-1"), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    /// Serve one scripted HTTP response per request, repeating the last one,
+    /// and count the requests received.
+    async fn stub_server(responses: &[(u16, &str, &str)]) -> (String, Arc<Mutex<usize>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let mut script: VecDeque<String> = responses
+            .iter()
+            .map(|(status, headers, body)| {
+                format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+                    status,
+                    body.len(),
+                    headers,
+                    body
+                )
+            })
+            .collect();
+        let hits = Arc::new(Mutex::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                *counter.lock().unwrap() += 1;
+                let response = if script.len() > 1 { script.pop_front().unwrap() } else { script[0].clone() };
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        (base_url, hits)
+    }
+
+    async fn poll(base_url: &str, deadline: Duration, progress: &Mutex<Vec<u32>>) -> AppResult<TaskStatusResponse> {
+        let client = Client::builder().no_proxy().build().unwrap();
+        poll_task(&client, base_url, "key", MeshTaskKind::ImageTo3d, "task-1", policy(deadline), |p| {
+            progress.lock().unwrap().push(p)
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn poll_task_retries_transient_errors_until_success() {
+        let (base_url, hits) = stub_server(&[
+            (503, "Retry-After: 0\r\n", "{}"),
+            (200, "", r#"{"status":"IN_PROGRESS","progress":40}"#),
+            (200, "", r#"{"status":"SUCCEEDED","progress":100,"model_urls":{"glb":"https://assets/model.glb"}}"#),
+        ])
+        .await;
+        let progress = Mutex::new(Vec::new());
+
+        let task = poll(&base_url, Duration::from_secs(10), &progress).await.unwrap();
+        assert_eq!(task.model_urls.and_then(|urls| urls.glb).as_deref(), Some("https://assets/model.glb"));
+        assert_eq!(*progress.lock().unwrap(), [40]);
+        assert_eq!(*hits.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn poll_task_reports_failed_tasks() {
+        let (base_url, _) =
+            stub_server(&[(200, "", r#"{"status":"FAILED","task_error":{"message":"Image has no subject"}}"#)]).await;
+
+        let err = poll(&base_url, Duration::from_secs(10), &Mutex::new(Vec::new())).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::TaskFailed);
+        assert!(err.message.contains("Image has no subject"), "{}", err.message);
+    }
+
+    #[tokio::test]
+    async fn poll_task_stops_on_fatal_errors() {
+        let (base_url, hits) = stub_server(&[(401, "", r#"{"message":"Invalid API key"}"#)]).await;
+
+        let err = poll(&base_url, Duration::from_secs(10), &Mutex::new(Vec::new())).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+        assert_eq!(*hits.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn poll_task_times_out_at_the_deadline() {
+        let (base_url, hits) = stub_server(&[(200, "", r#"{"status":"IN_PROGRESS","progress":10}"#)]).await;
+
+        let err = poll(&base_url, Duration::from_millis(100), &Mutex::new(Vec::new())).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::Timeout);
+        assert!(*hits.lock().unwrap() > 1);
+    }
 }
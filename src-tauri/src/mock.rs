@@ -0,0 +1,224 @@
+//! Offline stand-in for Gemini and Meshy. Select it with `FFR_PROVIDER=mock`
+//! to run the whole generation pipeline without network access or credits.
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::env;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::error::{AppError, AppResult, ErrorCode, Provider};
+use crate::gemini::RobotStatus;
+use crate::progress::{PipelineStage, ProgressReporter};
+use crate::providers::{ConceptImageProvider, MeshProvider, MeshTaskKind, StatsProvider};
+use crate::stats::Nutrition;
+
+const PLACEHOLDER_PNG: &[u8] = include_bytes!("../resources/mock/placeholder.png");
+const SAMPLE_GLB: &[u8] = include_bytes!("../resources/mock/sample.glb");
+const SAMPLE_GLB_URL: &str = "mock://sample.glb";
+
+/// Robot name and the food it was "made" from.
+const MOCK_NAMES: &[(&str, &str)] = &[
+    ("Oishii Ramen Striker", "ラーメン"),
+    ("Oishii Onigiri Guardian", "おにぎり"),
+    ("Oishii Curry Blaster", "カレーライス"),
+    ("Oishii Sushi Lancer", "寿司"),
+];
+
+/// Pipeline stage at which the mock reports an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFailure {
+    Stats,
+    Image,
+    Mesh,
+    Rig,
+    Animation,
+    Download,
+}
+
+impl MockFailure {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "stats" => Some(MockFailure::Stats),
+            "image" => Some(MockFailure::Image),
+            "mesh" => Some(MockFailure::Mesh),
+            "rig" => Some(MockFailure::Rig),
+            "animation" => Some(MockFailure::Animation),
+            "download" => Some(MockFailure::Download),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// Delay per simulated request or progress tick.
+    pub latency: Duration,
+    /// Number of progress ticks each simulated remote task takes.
+    pub progress_steps: u32,
+    pub fail_at: Option<MockFailure>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(300),
+            progress_steps: 5,
+            fail_at: None,
+        }
+    }
+}
+
+impl MockConfig {
+    /// Reads `FFR_MOCK_LATENCY_MS`, `FFR_MOCK_PROGRESS_STEPS` and `FFR_MOCK_FAIL_AT`
+    /// (`stats`, `image`, `mesh`, `rig`, `animation` or `download`).
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            latency: env::var("FFR_MOCK_LATENCY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.latency),
+            progress_steps: env::var("FFR_MOCK_PROGRESS_STEPS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.progress_steps),
+            fail_at: env::var("FFR_MOCK_FAIL_AT").ok().and_then(|v| MockFailure::parse(&v)),
+        }
+    }
+}
+
+/// Deterministic provider for all three pipeline roles.
+pub struct MockProvider {
+    config: MockConfig,
+}
+
+/// FNV-1a, so the same photo always yields the same robot.
+fn fingerprint(data: &str) -> u64 {
+    data.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl MockProvider {
+    pub fn new(config: MockConfig) -> Self {
+        Self { config }
+    }
+
+    fn check(&self, stage: MockFailure) -> AppResult<()> {
+        if self.config.fail_at == Some(stage) {
+            let code = match stage {
+                MockFailure::Mesh | MockFailure::Rig | MockFailure::Animation => ErrorCode::TaskFailed,
+                _ => ErrorCode::ProviderError,
+            };
+            return Err(AppError::new(code, format!("Mock failure injected at {:?}", stage))
+                .with_provider(Provider::Mock));
+        }
+        Ok(())
+    }
+
+    async fn simulate_task(
+        &self,
+        progress: &ProgressReporter,
+        stage: PipelineStage,
+        subtask: Option<&str>,
+    ) {
+        let steps = self.config.progress_steps.max(1);
+        for step in 1..=steps {
+            sleep(self.config.latency).await;
+            let percent = step * 100 / steps;
+            match subtask {
+                Some(name) => progress.subtask_progress(stage, name, percent),
+                None => progress.stage_progress(stage, percent),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StatsProvider for MockProvider {
+    async fn generate_stats(&self, base64_image: String, _mime_type: &str) -> AppResult<RobotStatus> {
+        sleep(self.config.latency).await;
+        self.check(MockFailure::Stats)?;
+
+        let seed = fingerprint(&base64_image);
+        let (name, food) = MOCK_NAMES[((seed >> 48) % MOCK_NAMES.len() as u64) as usize];
+        Ok(RobotStatus {
+            name: name.to_string(),
+            lore: "オイシイ・インダストリーの試作機。オフライン検証用のモックデータです。".to_string(),
+            // Computed from the nutrition below by `stats::apply_formula`.
+            hp: 0,
+            atk: 0,
+            def: 0,
+            visual_description: "A boxy mock combat robot, extreme full body shot, feet completely visible.".to_string(),
+            nutrition: Nutrition {
+                food_name: Some(food.to_string()),
+                calories: Some(200.0 + (seed % 800) as f64),
+                protein: Some(((seed >> 8) % 40) as f64),
+                fiber: Some(((seed >> 24) % 12) as f64),
+            },
+            adjustments: Vec::new(),
+            formula_version: None,
+        })
+    }
+}
+
+#[async_trait]
+impl ConceptImageProvider for MockProvider {
+    async fn generate_image(&self, _prompt: String) -> AppResult<String> {
+        sleep(self.config.latency).await;
+        self.check(MockFailure::Image)?;
+        Ok(STANDARD.encode(PLACEHOLDER_PNG))
+    }
+}
+
+#[async_trait]
+impl MeshProvider for MockProvider {
+    async fn create_mesh_task(&self, _base64_image: String, _mime_type: &str) -> AppResult<String> {
+        Ok(format!("mock-mesh-{}", uuid::Uuid::new_v4()))
+    }
+
+    async fn wait_for_mesh(&self, progress: &ProgressReporter, _task_id: &str) -> AppResult<String> {
+        self.simulate_task(progress, PipelineStage::GeneratingMesh, None).await;
+        self.check(MockFailure::Mesh)?;
+        Ok(SAMPLE_GLB_URL.to_string())
+    }
+
+    async fn create_rig_task(&self, _mesh_task_id: &str) -> AppResult<String> {
+        Ok(format!("mock-rig-{}", uuid::Uuid::new_v4()))
+    }
+
+    async fn wait_for_rig(&self, progress: &ProgressReporter, _rig_task_id: &str) -> AppResult<()> {
+        self.simulate_task(progress, PipelineStage::Rigging, None).await;
+        self.check(MockFailure::Rig)
+    }
+
+    async fn create_animation_task(&self, _rig_task_id: &str, action_id: u32) -> AppResult<String> {
+        Ok(format!("mock-anim-{}-{}", action_id, uuid::Uuid::new_v4()))
+    }
+
+    async fn wait_for_animation(
+        &self,
+        progress: &ProgressReporter,
+        _task_id: &str,
+        name: &str,
+    ) -> AppResult<String> {
+        self.simulate_task(progress, PipelineStage::Animating, Some(name)).await;
+        self.check(MockFailure::Animation)?;
+        Ok(SAMPLE_GLB_URL.to_string())
+    }
+
+    async fn cancel_task(&self, _kind: MeshTaskKind, _task_id: &str) -> AppResult<()> {
+        Ok(())
+    }
+
+    async fn download_model(&self, url: &str) -> AppResult<Vec<u8>> {
+        self.check(MockFailure::Download)?;
+        if url != SAMPLE_GLB_URL {
+            return Err(AppError::not_found(format!("Unknown mock model URL: {}", url))
+                .with_provider(Provider::Mock));
+        }
+        Ok(SAMPLE_GLB.to_vec())
+    }
+}
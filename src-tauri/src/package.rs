@@ -0,0 +1,634 @@
+//! `.ffr` robot packages for sharing robots between installs.
+//!
+//! A package is a zip archive with this layout:
+//!
+//! ```text
+//! manifest.json          Manifest: format version plus size and SHA-256 of every other entry
+//! robot.json             db::RobotRecord, its file fields pointing at the entries below
+//! assets/<sha256>.<ext>  original photo, concept image, idle/attack GLBs
+//! ```
+//!
+//! Packages come from other people, so `import` checks every entry against
+//! the manifest and the size limits below before anything is stored.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::assets::AssetStore;
+use crate::clock;
+use crate::db::{self, RobotRecord};
+use crate::error::{AppError, AppResult};
+use crate::preprocess;
+use crate::settings::StatRanges;
+use crate::stats;
+
+/// Bump when the layout changes in a way older builds can't read.
+pub const FORMAT_VERSION: u32 = 1;
+pub const EXTENSION: &str = "ffr";
+
+const MANIFEST_PATH: &str = "manifest.json";
+const ROBOT_PATH: &str = "robot.json";
+
+/// Largest package `import` accepts.
+pub const MAX_PACKAGE_BYTES: u64 = 256 * 1024 * 1024;
+/// Largest single GLB; Meshy's animated models are well below this.
+pub const MAX_GLB_BYTES: u64 = 100 * 1024 * 1024;
+pub const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+const MAX_JSON_BYTES: u64 = 1024 * 1024;
+
+/// What to do when an imported robot's id is already in the database.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    Skip,
+    /// Replace the existing robot and its files.
+    Overwrite,
+    /// Import as a new robot with a fresh id.
+    Duplicate,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Imported,
+    Skipped,
+    /// The id exists and no policy was given; `robot` is the existing one.
+    /// Ask the user and import again with a `ConflictPolicy`.
+    Conflict,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportResult {
+    pub status: ImportStatus,
+    pub robot: RobotRecord,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Manifest {
+    pub format_version: u32,
+    /// Version of the app that wrote the package.
+    pub app_version: String,
+    pub exported_at: i64,
+    pub robot_id: String,
+    pub robot_name: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+fn zip_error(e: zip::result::ZipError) -> AppError {
+    AppError::internal(format!("Failed to write robot package: {}", e))
+}
+
+/// Package entry name for a robot file, e.g. `assets/<sha256>.glb`.
+fn entry_path(asset_id: &str, bytes: &[u8]) -> String {
+    let extension = Path::new(asset_id)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("bin")
+        .to_lowercase();
+    format!("assets/{}.{}", preprocess::content_hash(bytes), extension)
+}
+
+/// Build the `.ffr` archive for `robot` in memory.
+pub fn build(robot: &RobotRecord, store: &AssetStore) -> AppResult<Vec<u8>> {
+    let mut packaged = robot.clone();
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    for field in [
+        &mut packaged.original_image_path,
+        &mut packaged.image_path,
+        &mut packaged.model_path,
+        &mut packaged.attack_model_path,
+    ] {
+        let bytes = store.read(field)?;
+        let path = entry_path(field, &bytes);
+        // The static-model fallback uses one GLB for both models; store it once.
+        if !files.iter().any(|(existing, _)| *existing == path) {
+            files.push((path.clone(), bytes));
+        }
+        *field = path;
+    }
+    files.insert(0, (ROBOT_PATH.to_string(), serde_json::to_vec_pretty(&packaged)?));
+
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: clock::now_ms() / 1000,
+        robot_id: robot.id.clone(),
+        robot_name: robot.name.clone(),
+        entries: files
+            .iter()
+            .map(|(path, bytes)| ManifestEntry {
+                path: path.clone(),
+                size: bytes.len() as u64,
+                sha256: preprocess::content_hash(bytes),
+            })
+            .collect(),
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Images and GLBs are already compressed.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip.start_file(MANIFEST_PATH, deflated).map_err(zip_error)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    for (path, bytes) in &files {
+        let options = if path == ROBOT_PATH { deflated } else { stored };
+        zip.start_file(path.as_str(), options).map_err(zip_error)?;
+        zip.write_all(bytes)?;
+    }
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+/// Write `robot` as a package at `destination`.
+pub fn export(robot: &RobotRecord, store: &AssetStore, destination: &Path) -> AppResult<()> {
+    let bytes = build(robot, store)?;
+    let tmp = destination.with_extension("ffr.tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, destination)?;
+    Ok(())
+}
+
+/// Default file name for a robot's package, safe on every platform. The
+/// start of the id keeps two robots with the same name from overwriting each
+/// other's package; exporting the same robot again replaces its file.
+pub fn file_name(robot: &RobotRecord) -> String {
+    let sanitize = |text: &str| -> String {
+        let safe: String = text
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        safe.trim_matches('_').to_string()
+    };
+    let id: String = sanitize(&robot.id).chars().take(8).collect();
+    let name = sanitize(&robot.name);
+    if name.is_empty() {
+        format!("{}.{}", id, EXTENSION)
+    } else {
+        format!("{}-{}.{}", name, id, EXTENSION)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::invalid_input(format!("Invalid robot package: {}", message.into()))
+}
+
+/// Read one entry, refusing to inflate more than `limit` bytes.
+fn read_entry<R: Read + std::io::Seek>(zip: &mut ZipArchive<R>, path: &str, limit: u64) -> AppResult<Vec<u8>> {
+    let entry = zip.by_name(path).map_err(|_| invalid(format!("{} is missing", path)))?;
+    if entry.size() > limit {
+        return Err(invalid(format!("{} is {} bytes, more than the {} allowed", path, entry.size(), limit)));
+    }
+    let mut bytes = Vec::new();
+    // The declared size can lie, so cap the actual read as well.
+    entry.take(limit + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > limit {
+        return Err(invalid(format!("{} is larger than the {} bytes allowed", path, limit)));
+    }
+    Ok(bytes)
+}
+
+/// Structural check of a binary glTF: header, declared length, and a
+/// parseable JSON chunk followed by well-formed chunks.
+fn validate_glb(path: &str, bytes: &[u8]) -> AppResult<()> {
+    let u32_at = |offset: usize| -> Option<u32> {
+        bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    if bytes.get(0..4) != Some(b"glTF".as_slice()) || u32_at(4) != Some(2) {
+        return Err(invalid(format!("{} is not a glTF 2.0 binary", path)));
+    }
+    if u32_at(8).map(|len| len as usize) != Some(bytes.len()) {
+        return Err(invalid(format!("{} is truncated or has trailing data", path)));
+    }
+
+    let mut offset = 12;
+    let mut first = true;
+    while offset < bytes.len() {
+        let (Some(length), Some(kind)) = (u32_at(offset), u32_at(offset + 4)) else {
+            return Err(invalid(format!("{} has a malformed chunk header", path)));
+        };
+        let start = offset + 8;
+        let Some(chunk) = start.checked_add(length as usize).and_then(|end| bytes.get(start..end)) else {
+            return Err(invalid(format!("{} has a chunk past the end of the file", path)));
+        };
+        if first {
+            // 0x4E4F534A is "JSON"; the glTF spec requires it to come first.
+            if kind != 0x4E4F_534A || serde_json::from_slice::<serde_json::Value>(chunk).is_err() {
+                return Err(invalid(format!("{} does not start with a valid JSON chunk", path)));
+            }
+            first = false;
+        }
+        offset = start + length as usize;
+    }
+    if first {
+        return Err(invalid(format!("{} has no chunks", path)));
+    }
+    Ok(())
+}
+
+/// Check the fields of an untrusted `robot.json`: the id must be a UUID (it
+/// names the robot's asset folder), the name must not be empty, and the stats
+/// are brought into this install's configured ranges.
+fn validate_robot(robot: &mut RobotRecord, ranges: &StatRanges) -> AppResult<()> {
+    let canonical = uuid::Uuid::parse_str(&robot.id).map(|id| id.to_string());
+    if canonical.as_deref() != Ok(robot.id.as_str()) {
+        return Err(invalid(format!("robot id {:?} is not a UUID", robot.id)));
+    }
+    if robot.name.trim().is_empty() {
+        return Err(invalid("robot name is empty"));
+    }
+    let adjustments = stats::check_stats(&mut robot.hp, &mut robot.atk, &mut robot.def, ranges)
+        .map_err(|e| invalid(e.message))?;
+    robot.stat_adjustments.extend(adjustments);
+    robot.stats_adjusted |= !robot.stat_adjustments.is_empty();
+    Ok(())
+}
+
+/// Read the package file at `path`, refusing files over `MAX_PACKAGE_BYTES`
+/// before loading them.
+pub fn read_package(path: &Path) -> AppResult<Vec<u8>> {
+    if fs::metadata(path)?.len() > MAX_PACKAGE_BYTES {
+        return Err(invalid(format!("larger than {} MB", MAX_PACKAGE_BYTES / 1024 / 1024)));
+    }
+    Ok(fs::read(path)?)
+}
+
+/// A package that passed every check, not yet stored anywhere.
+struct Unpacked {
+    robot: RobotRecord,
+    /// Bytes and extension of each file, in the order of the robot's file fields.
+    files: Vec<(Vec<u8>, &'static str)>,
+}
+
+/// Check `package` against its manifest and the size limits.
+fn unpack(package: &[u8], ranges: &StatRanges) -> AppResult<Unpacked> {
+    if package.len() as u64 > MAX_PACKAGE_BYTES {
+        return Err(invalid(format!("larger than {} MB", MAX_PACKAGE_BYTES / 1024 / 1024)));
+    }
+    let mut zip = ZipArchive::new(Cursor::new(package)).map_err(|e| invalid(e.to_string()))?;
+
+    let manifest: Manifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_PATH, MAX_JSON_BYTES)?)
+        .map_err(|e| invalid(format!("unreadable manifest: {}", e)))?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(invalid(format!(
+            "format version {} is newer than this app supports ({}); update the app to import it",
+            manifest.format_version, FORMAT_VERSION
+        )));
+    }
+
+    let mut read_checked = |path: &str, limit: u64| -> AppResult<Vec<u8>> {
+        let entry = manifest
+            .entries
+            .iter()
+            .find(|e| e.path == path)
+            .ok_or_else(|| invalid(format!("{} is not listed in the manifest", path)))?;
+        let bytes = read_entry(&mut zip, path, limit)?;
+        if bytes.len() as u64 != entry.size || preprocess::content_hash(&bytes) != entry.sha256 {
+            return Err(invalid(format!("checksum mismatch for {}", path)));
+        }
+        Ok(bytes)
+    };
+
+    let mut robot: RobotRecord = serde_json::from_slice(&read_checked(ROBOT_PATH, MAX_JSON_BYTES)?)
+        .map_err(|e| invalid(format!("unreadable robot.json: {}", e)))?;
+    validate_robot(&mut robot, ranges)?;
+
+    // Read and validate every file before touching the store or the database.
+    let mut files = Vec::new();
+    for (is_model, path) in [
+        (false, &robot.original_image_path),
+        (false, &robot.image_path),
+        (true, &robot.model_path),
+        (true, &robot.attack_model_path),
+    ] {
+        if !path.starts_with("assets/") || path.contains("..") {
+            return Err(invalid(format!("unexpected file path {}", path)));
+        }
+        if is_model {
+            let bytes = read_checked(path, MAX_GLB_BYTES)?;
+            validate_glb(path, &bytes)?;
+            files.push((bytes, "glb"));
+        } else {
+            let bytes = read_checked(path, MAX_IMAGE_BYTES)?;
+            let extension = preprocess::sniff_format(&bytes)?.extensions_str()[0];
+            files.push((bytes, extension));
+        }
+    }
+    Ok(Unpacked { robot, files })
+}
+
+/// Validate `package` and add its robot to the database, applying
+/// `on_conflict` when the robot's id already exists. Stats outside `ranges`
+/// are handled like generated ones: clamped and flagged, or rejected.
+///
+/// `conn` is only locked for the conflict check and the final transaction, so
+/// unzipping and validating a large package doesn't stall other commands.
+pub fn import(
+    conn: &Mutex<Connection>,
+    store: &AssetStore,
+    package: &[u8],
+    ranges: &StatRanges,
+    on_conflict: Option<ConflictPolicy>,
+) -> AppResult<ImportResult> {
+    let Unpacked { mut robot, files } = unpack(package, ranges)?;
+
+    let existing = db::get_robot(&*conn.lock()?, &robot.id)?;
+    let overwrite = match (existing, on_conflict) {
+        (None, _) => false,
+        (Some(existing), None) => return Ok(ImportResult { status: ImportStatus::Conflict, robot: existing }),
+        (Some(existing), Some(ConflictPolicy::Skip)) => {
+            return Ok(ImportResult { status: ImportStatus::Skipped, robot: existing })
+        }
+        (Some(_), Some(ConflictPolicy::Overwrite)) => true,
+        (Some(_), Some(ConflictPolicy::Duplicate)) => {
+            robot.id = uuid::Uuid::new_v4().to_string();
+            false
+        }
+    };
+
+    let mut ids = Vec::new();
+    for (bytes, extension) in &files {
+        ids.push(store.put(&robot.id, bytes, extension)?);
+    }
+    robot.original_image_path = ids[0].clone();
+    robot.image_path = ids[1].clone();
+    robot.model_path = ids[2].clone();
+    robot.attack_model_path = ids[3].clone();
+
+    {
+        let mut conn = conn.lock()?;
+        let tx = conn.transaction()?;
+        if overwrite {
+            db::delete_robot(&tx, &robot.id)?;
+        }
+        db::insert_robot(&tx, &robot)?;
+        tx.commit()?;
+    }
+    if overwrite {
+        // The replaced robot's files that the new one doesn't share.
+        store.retain(&robot.id, &ids);
+    }
+
+    Ok(ImportResult { status: ImportStatus::Imported, robot })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::AdjustmentKind;
+    use crate::test_support::TestInstall;
+
+    const ROBOT_ID: &str = "0f8e6a2c-1111-4c4c-8d8d-000000000001";
+
+    fn robot(id: &str, name: &str) -> RobotRecord {
+        RobotRecord {
+            id: id.to_string(),
+            name: name.to_string(),
+            lore: "Forged in a bowl of broth".to_string(),
+            hp: 1000,
+            atk: 50,
+            def: 20,
+            generation_time_ms: 60_000,
+            ..Default::default()
+        }
+    }
+
+    /// Smallest well-formed GLB: a header and one JSON chunk.
+    fn glb(json: &str) -> Vec<u8> {
+        let mut chunk = json.as_bytes().to_vec();
+        while !chunk.len().is_multiple_of(4) {
+            chunk.push(b' ');
+        }
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&(12 + 8 + chunk.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&0x4E4F_534Au32.to_le_bytes());
+        bytes.extend_from_slice(&chunk);
+        bytes
+    }
+
+    fn png() -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut out, image::ImageFormat::Png)
+            .unwrap();
+        out.into_inner()
+    }
+
+    /// A package for a robot with real files, built from a scratch install.
+    fn package(robot: &RobotRecord) -> Vec<u8> {
+        let source = TestInstall::new();
+        let mut robot = robot.clone();
+        robot.original_image_path = source.store.put(&robot.id, &png(), "png").unwrap();
+        robot.image_path = robot.original_image_path.clone();
+        robot.model_path = source.store.put(&robot.id, &glb(r#"{"asset":{"version":"2.0"}}"#), "glb").unwrap();
+        robot.attack_model_path = robot.model_path.clone();
+        build(&robot, &source.store).unwrap()
+    }
+
+    /// Rewrite every entry of `package` through `edit`. With `fix_manifest`
+    /// the manifest is updated to match, as a deliberate forger would.
+    fn repack(package: &[u8], edit: impl Fn(&str, Vec<u8>) -> Vec<u8>, fix_manifest: bool) -> Vec<u8> {
+        let mut zip = ZipArchive::new(Cursor::new(package)).unwrap();
+        let mut entries = Vec::new();
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).unwrap();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes).unwrap();
+            let path = entry.name().to_string();
+            let bytes = if path == MANIFEST_PATH { bytes } else { edit(&path, bytes) };
+            entries.push((path, bytes));
+        }
+        if fix_manifest {
+            let mut manifest: Manifest = serde_json::from_slice(&entries[0].1).unwrap();
+            for entry in &mut manifest.entries {
+                let (_, bytes) = entries.iter().find(|(path, _)| *path == entry.path).unwrap();
+                entry.size = bytes.len() as u64;
+                entry.sha256 = preprocess::content_hash(bytes);
+            }
+            entries[0].1 = serde_json::to_vec(&manifest).unwrap();
+        }
+
+        let mut out = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, bytes) in entries {
+            out.start_file(path, SimpleFileOptions::default()).unwrap();
+            out.write_all(&bytes).unwrap();
+        }
+        out.finish().unwrap().into_inner()
+    }
+
+    fn edit_robot(package: &[u8], edit: impl Fn(&mut RobotRecord)) -> Vec<u8> {
+        repack(
+            package,
+            |path, bytes| {
+                if path != ROBOT_PATH {
+                    return bytes;
+                }
+                let mut robot: RobotRecord = serde_json::from_slice(&bytes).unwrap();
+                edit(&mut robot);
+                serde_json::to_vec(&robot).unwrap()
+            },
+            true,
+        )
+    }
+
+    fn import_into(target: &TestInstall, package: &[u8], on_conflict: Option<ConflictPolicy>) -> AppResult<ImportResult> {
+        import(&target.db, &target.store, package, &StatRanges::default(), on_conflict)
+    }
+
+    #[test]
+    fn round_trips_through_build_and_import() {
+        let original = robot(ROBOT_ID, "Ramen Titan");
+        let package = package(&original);
+        let target = TestInstall::new();
+
+        let result = import_into(&target, &package, None).unwrap();
+        assert_eq!(result.status, ImportStatus::Imported);
+        let imported = db::get_robot(&target.conn(), ROBOT_ID).unwrap().unwrap();
+        assert_eq!(imported.name, original.name);
+        assert_eq!((imported.hp, imported.atk, imported.def), (1000, 50, 20));
+        assert!(!imported.stats_adjusted);
+        assert_eq!(target.store.read(&imported.image_path).unwrap(), png());
+        assert!(target.store.read(&imported.model_path).unwrap().starts_with(b"glTF"));
+    }
+
+    #[test]
+    fn read_package_enforces_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("robot.ffr");
+        let package = package(&robot(ROBOT_ID, "Ramen Titan"));
+        fs::write(&path, &package).unwrap();
+        assert_eq!(read_package(&path).unwrap(), package);
+
+        // Sparse, so the test doesn't write 256 MB.
+        fs::File::create(&path).unwrap().set_len(MAX_PACKAGE_BYTES + 1).unwrap();
+        assert!(read_package(&path).is_err());
+    }
+
+    #[test]
+    fn conflicts_follow_the_policy() {
+        let package = package(&robot(ROBOT_ID, "Ramen Titan"));
+        let target = TestInstall::new();
+        import_into(&target, &package, None).unwrap();
+
+        let conflict = import_into(&target, &package, None).unwrap();
+        assert_eq!(conflict.status, ImportStatus::Conflict);
+        let skipped = import_into(&target, &package, Some(ConflictPolicy::Skip)).unwrap();
+        assert_eq!(skipped.status, ImportStatus::Skipped);
+        let copy = import_into(&target, &package, Some(ConflictPolicy::Duplicate)).unwrap();
+        assert_ne!(copy.robot.id, ROBOT_ID);
+        let overwritten = import_into(&target, &package, Some(ConflictPolicy::Overwrite)).unwrap();
+        assert_eq!(overwritten.robot.id, ROBOT_ID);
+        assert_eq!(db::get_robots(&target.conn()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_tampered_entries() {
+        let package = package(&robot(ROBOT_ID, "Ramen Titan"));
+        let tampered = repack(
+            &package,
+            |path, mut bytes| {
+                if path.ends_with(".png") {
+                    bytes.push(0);
+                }
+                bytes
+            },
+            false,
+        );
+        let target = TestInstall::new();
+        let err = import_into(&target, &tampered, None).unwrap_err();
+        assert!(err.message.contains("checksum mismatch"), "{}", err.message);
+        assert!(db::get_robots(&target.conn()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_glb_even_with_matching_checksum() {
+        let package = package(&robot(ROBOT_ID, "Ramen Titan"));
+        let broken = repack(
+            &package,
+            |path, mut bytes| {
+                if path.ends_with(".glb") {
+                    bytes.truncate(bytes.len() - 4);
+                }
+                bytes
+            },
+            true,
+        );
+        let target = TestInstall::new();
+        let err = import_into(&target, &broken, None).unwrap_err();
+        assert!(err.message.contains("truncated"), "{}", err.message);
+    }
+
+    #[test]
+    fn validate_glb_checks_structure() {
+        validate_glb("a.glb", &glb(r#"{"asset":{"version":"2.0"}}"#)).unwrap();
+        assert!(validate_glb("a.glb", b"not a model").is_err());
+        assert!(validate_glb("a.glb", &glb("not json")).is_err());
+
+        let mut huge_chunk = glb("{}  ");
+        huge_chunk[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(validate_glb("a.glb", &huge_chunk).is_err());
+    }
+
+    #[test]
+    fn read_entry_enforces_size_limit() {
+        let package = package(&robot(ROBOT_ID, "Ramen Titan"));
+        let mut zip = ZipArchive::new(Cursor::new(package.as_slice())).unwrap();
+        let err = read_entry(&mut zip, ROBOT_PATH, 16).unwrap_err();
+        assert!(err.message.contains("allowed"), "{}", err.message);
+    }
+
+    #[test]
+    fn rejects_invalid_robot_fields() {
+        let package = package(&robot(ROBOT_ID, "Ramen Titan"));
+        let target = TestInstall::new();
+
+        let bad_id = edit_robot(&package, |r| r.id = "a/b".to_string());
+        let err = import_into(&target, &bad_id, None).unwrap_err();
+        assert!(err.message.contains("not a UUID"), "{}", err.message);
+
+        let no_name = edit_robot(&package, |r| r.name = "  ".to_string());
+        assert!(import_into(&target, &no_name, None).is_err());
+        assert!(db::get_robots(&target.conn()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clamps_and_flags_out_of_range_stats() {
+        let package = edit_robot(&package(&robot(ROBOT_ID, "Ramen Titan")), |r| r.hp = 999_999);
+        let target = TestInstall::new();
+        let imported = import_into(&target, &package, None).unwrap().robot;
+        assert_eq!(imported.hp, StatRanges::default().hp.max);
+        assert!(imported.stats_adjusted);
+        assert_eq!(imported.stat_adjustments[0].kind, AdjustmentKind::Clamped);
+        assert_eq!(imported.stat_adjustments[0].original, Some(999_999));
+
+        let reject = StatRanges {
+            out_of_range: stats::OutOfRangePolicy::Reject,
+            ..StatRanges::default()
+        };
+        let target = TestInstall::new();
+        assert!(import(&target.db, &target.store, &package, &reject, None).is_err());
+    }
+
+    #[test]
+    fn file_names_differ_for_robots_with_the_same_name() {
+        let a = file_name(&robot("0f8e6a2c-1111-4c4c-8d8d-000000000001", "Ramen Titan"));
+        let b = file_name(&robot("7b1d9e44-2222-4c4c-8d8d-000000000002", "Ramen Titan"));
+        assert_eq!(a, "Ramen_Titan-0f8e6a2c.ffr");
+        assert_ne!(a, b);
+        assert_eq!(file_name(&robot("0f8e6a2c-1111", "../..")), "0f8e6a2c.ffr");
+    }
+}
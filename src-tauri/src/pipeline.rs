@@ -0,0 +1,490 @@
+use crate::assets::AssetStore;
+use crate::error::{AppError, AppResult, ErrorCode, Provider};
+use crate::providers::{MeshTaskKind, Providers};
+use crate::settings::SettingsStore;
+use crate::{db, gemini, preprocess, stats};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+use crate::clock::now_ms;
+use crate::progress::{PipelineStage, ProgressReporter};
+
+pub const DEFAULT_MAX_CONCURRENT_JOBS: usize = 2;
+
+/// Uploads whose perceptual hashes differ in at most this many of 64 bits
+/// are treated as the same photo.
+pub const DUPLICATE_MAX_DISTANCE: u32 = 6;
+
+/// Limits how many generation jobs run at once (one per construction dock by
+/// default) and holds the cancellation tokens of queued and running jobs.
+pub struct JobQueue {
+    slots: Semaphore,
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl JobQueue {
+    pub fn new(max_concurrent_jobs: usize) -> Self {
+        Self {
+            slots: Semaphore::new(max_concurrent_jobs.max(1)),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Concurrency from `FFR_MAX_CONCURRENT_JOBS`, falling back to `DEFAULT_MAX_CONCURRENT_JOBS`.
+    pub fn from_env() -> Self {
+        let max = std::env::var("FFR_MAX_CONCURRENT_JOBS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_JOBS);
+        Self::new(max)
+    }
+
+    fn register(&self, job_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.insert(job_id.to_string(), token.clone());
+        }
+        token
+    }
+
+    fn unregister(&self, job_id: &str) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.remove(job_id);
+        }
+    }
+
+    /// Signal the job to stop. Returns false if no such job is queued or running.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.tokens.lock() {
+            Ok(tokens) => match tokens.get(job_id) {
+                Some(token) => {
+                    token.cancel();
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+struct JobStarted {
+    job_id: String,
+}
+
+#[derive(serde::Serialize, Clone)]
+struct PartialStats {
+    job_id: String,
+    stats: gemini::RobotStatus,
+}
+
+#[derive(serde::Serialize, Clone)]
+struct PartialImages {
+    job_id: String,
+    original_image_path: String,
+    image_path: String,
+}
+
+/// An existing robot built from (almost) the same photo as an upload.
+#[derive(serde::Serialize, Clone)]
+pub struct DuplicateMatch {
+    pub robot: db::RobotRecord,
+    /// Differing hash bits; 0 means visually identical.
+    pub distance: u32,
+}
+
+/// The robot whose photo is closest to `perceptual_hash`, if any is within `DUPLICATE_MAX_DISTANCE`.
+pub fn find_duplicate(conn: &rusqlite::Connection, perceptual_hash: &str) -> AppResult<Option<DuplicateMatch>> {
+    let closest = db::robot_perceptual_hashes(conn)?
+        .into_iter()
+        .filter_map(|(id, hash)| Some((id, preprocess::hash_distance(perceptual_hash, &hash)?)))
+        .filter(|(_, distance)| *distance <= DUPLICATE_MAX_DISTANCE)
+        .min_by_key(|(_, distance)| *distance);
+    match closest {
+        Some((id, distance)) => Ok(db::get_robot(conn, &id)?.map(|robot| DuplicateMatch { robot, distance })),
+        None => Ok(None),
+    }
+}
+
+pub async fn prepare_upload(base64_image: String) -> AppResult<preprocess::PreparedImage> {
+    // Decoding and resizing a phone photo takes a moment; keep it off the async runtime.
+    tokio::task::spawn_blocking(move || preprocess::prepare(&preprocess::decode_base64(&base64_image)?))
+        .await
+        .map_err(|e| AppError::internal(format!("Image preprocessing panicked: {}", e)))?
+}
+
+/// Look for an existing robot made from the same photo, without starting a job.
+pub async fn check_duplicate(app: &AppHandle, base64_image: String) -> AppResult<Option<DuplicateMatch>> {
+    let image = prepare_upload(base64_image).await?;
+    let state = app.state::<Mutex<rusqlite::Connection>>();
+    let conn = state.lock()?;
+    find_duplicate(&conn, &image.perceptual_hash)
+}
+
+fn save_job(app: &AppHandle, job: &mut db::GenerationJob) -> AppResult<()> {
+    job.updated_at_ms = now_ms();
+    let state = app.state::<Mutex<rusqlite::Connection>>();
+    let conn = state.lock()?;
+    db::update_job(&conn, job)
+}
+
+/// Persist the uploaded image and a new job row, then queue the job and wait for it to finish.
+///
+/// Unless `allow_duplicate` is set, an upload that matches an existing robot's
+/// photo fails with `ErrorCode::Duplicate` before any provider is called.
+pub async fn start_job(app: &AppHandle, base64_image: String, allow_duplicate: bool) -> AppResult<db::RobotRecord> {
+    let image = prepare_upload(base64_image).await?;
+
+    if !allow_duplicate {
+        let state = app.state::<Mutex<rusqlite::Connection>>();
+        let conn = state.lock()?;
+        if let Some(duplicate) = find_duplicate(&conn, &image.perceptual_hash)? {
+            return Err(AppError::new(
+                ErrorCode::Duplicate,
+                format!("This photo was already used to build {}", duplicate.robot.name),
+            ));
+        }
+    }
+
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let original_image_path = app.state::<AssetStore>().put(&job_id, &image.bytes, image.extension())?;
+
+    let now = now_ms();
+    let job = db::GenerationJob {
+        id: job_id,
+        status: db::JobStatus::Queued,
+        original_image_path,
+        image_hash: Some(image.content_hash),
+        perceptual_hash: Some(image.perceptual_hash),
+        stats_json: None,
+        generated_image_path: None,
+        image_task_id: None,
+        rig_task_id: None,
+        idle_anim_task_id: None,
+        attack_anim_task_id: None,
+        rigging_error: None,
+        robot_id: None,
+        error: None,
+        created_at_ms: now,
+        updated_at_ms: now,
+    };
+
+    {
+        let state = app.state::<Mutex<rusqlite::Connection>>();
+        let conn = state.lock()?;
+        db::insert_job(&conn, &job)?;
+    }
+
+    let _ = app.emit("pipeline-started", JobStarted { job_id: job.id.clone() });
+
+    run_job(app, job).await
+}
+
+/// Re-queue every job that was queued or running when the app last exited.
+pub async fn resume_unfinished_jobs(app: AppHandle) {
+    let jobs = {
+        let state = app.state::<Mutex<rusqlite::Connection>>();
+        let conn = match state.lock() {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Failed to lock database to resume jobs: {}", e);
+                return;
+            }
+        };
+        db::get_unfinished_jobs(&conn)
+    };
+
+    let jobs = match jobs {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("Failed to load unfinished generation jobs: {}", e);
+            return;
+        }
+    };
+
+    for job in jobs {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let job_id = job.id.clone();
+            println!("Resuming generation job {}", job_id);
+            if let Err(e) = run_job(&app, job).await {
+                eprintln!("Resumed generation job {} failed: {}", job_id, e);
+            }
+        });
+    }
+}
+
+/// Wait for a free slot in the queue, then drive `job` from its first
+/// unfinished step to completion, recording the failure on the job row if any
+/// step errors. Cancelling the job drops the in-flight step, which also stops
+/// any Meshy polling loop it was running.
+pub async fn run_job(app: &AppHandle, mut job: db::GenerationJob) -> AppResult<db::RobotRecord> {
+    let queue = app.state::<JobQueue>();
+    let token = queue.register(&job.id);
+    let progress = ProgressReporter::new(app, &job.id, job.created_at_ms);
+
+    let outcome = tokio::select! {
+        res = async {
+            if queue.slots.available_permits() == 0 {
+                progress.stage(PipelineStage::Queued);
+            }
+            let _slot = queue
+                .slots
+                .acquire()
+                .await
+                .map_err(|e| AppError::internal(e.to_string()))?;
+
+            job.status = db::JobStatus::Running;
+            save_job(app, &mut job)?;
+            advance(app, &progress, &mut job).await
+        } => Some(res),
+        _ = token.cancelled() => None,
+    };
+    queue.unregister(&job.id);
+
+    match outcome {
+        Some(Ok(robot)) => {
+            progress.completed(&robot.id);
+            Ok(robot)
+        }
+        Some(Err(err)) => {
+            progress.failed(&err);
+            job.status = db::JobStatus::Failed;
+            job.error = Some(err.message.clone());
+            if let Err(e) = save_job(app, &mut job) {
+                eprintln!("Failed to record failure of job {}: {}", job.id, e);
+            }
+            Err(err)
+        }
+        None => {
+            job.status = db::JobStatus::Cancelled;
+            job.error = Some(AppError::cancelled().message);
+            if let Err(e) = save_job(app, &mut job) {
+                eprintln!("Failed to record cancellation of job {}: {}", job.id, e);
+            }
+            cancel_remote_tasks(app, &job).await;
+            remove_job_files(app, &job);
+            progress.cancelled();
+            Err(AppError::cancelled())
+        }
+    }
+}
+
+async fn cancel_remote_tasks(app: &AppHandle, job: &db::GenerationJob) {
+    let providers = app.state::<Providers>();
+    let tasks = [
+        (MeshTaskKind::ImageTo3d, &job.image_task_id),
+        (MeshTaskKind::Rigging, &job.rig_task_id),
+        (MeshTaskKind::Animation, &job.idle_anim_task_id),
+        (MeshTaskKind::Animation, &job.attack_anim_task_id),
+    ];
+    for (kind, task_id) in tasks {
+        if let Some(task_id) = task_id {
+            if let Err(e) = providers.mesh.cancel_task(kind, task_id).await {
+                eprintln!("Failed to cancel {:?} task {}: {}", kind, task_id, e);
+            }
+        }
+    }
+}
+
+/// Delete every file the job may have written so far.
+fn remove_job_files(app: &AppHandle, job: &db::GenerationJob) {
+    let assets = app.state::<AssetStore>();
+    assets.remove_legacy(&job.original_image_path);
+    if let Some(path) = &job.generated_image_path {
+        assets.remove_legacy(path);
+    }
+    assets.remove_robot(&job.id);
+}
+
+async fn advance(
+    app: &AppHandle,
+    progress: &ProgressReporter,
+    job: &mut db::GenerationJob,
+) -> AppResult<db::RobotRecord> {
+    let providers = app.state::<Providers>().inner().clone();
+
+    // Step 1: stats
+    let stats: gemini::RobotStatus = match &job.stats_json {
+        Some(json) => serde_json::from_str(json)
+            .map_err(|e| AppError::internal(format!("Corrupt saved stats: {}", e)))?,
+        None => {
+            progress.stage(PipelineStage::AnalyzingFood);
+            let orig_image_bytes = app.state::<AssetStore>().read(&job.original_image_path)?;
+            let mime_type = preprocess::mime_type(&orig_image_bytes);
+            let mut stats = providers.stats.generate_stats(STANDARD.encode(orig_image_bytes), mime_type).await?;
+            stats::finalize(&mut stats, &app.state::<Arc<SettingsStore>>().get().stats)?;
+            job.stats_json = Some(serde_json::to_string(&stats)?);
+            save_job(app, job)?;
+            stats
+        }
+    };
+
+    // We can emit partial stats to UI
+    let _ = app.emit("pipeline-stats", PartialStats {
+        job_id: job.id.clone(),
+        stats: stats.clone(),
+    });
+
+    // Step 2: concept image
+    let generated_image_path = match &job.generated_image_path {
+        Some(path) => path.clone(),
+        None => {
+            progress.stage(PipelineStage::GeneratingImage);
+            let gen_image_b64 = providers.image.generate_image(stats.visual_description.clone()).await?;
+
+            let gen_image_bytes = STANDARD.decode(&gen_image_b64).map_err(|e| AppError::invalid_response(Provider::Gemini, format!("Base64 Error (Gen): {}", e)))?;
+            let format = preprocess::sniff_format(&gen_image_bytes).map_err(|e| AppError::invalid_response(Provider::Gemini, e.message))?;
+            let path = app.state::<AssetStore>().put(&job.id, &gen_image_bytes, preprocess::extension(format))?;
+
+            job.generated_image_path = Some(path.clone());
+            save_job(app, job)?;
+            path
+        }
+    };
+
+    // Let frontend know we saved the images so it can show them early
+    let _ = app.emit("pipeline-images", PartialImages {
+        job_id: job.id.clone(),
+        original_image_path: job.original_image_path.clone(),
+        image_path: generated_image_path.clone(),
+    });
+
+    // Step 3: image-to-3D
+    progress.stage(PipelineStage::GeneratingMesh);
+    let task_id = match &job.image_task_id {
+        Some(task_id) => task_id.clone(),
+        None => {
+            let gen_image_bytes = app.state::<AssetStore>().read(&generated_image_path)?;
+            let mime_type = preprocess::mime_type(&gen_image_bytes);
+            let task_id = providers.mesh.create_mesh_task(STANDARD.encode(&gen_image_bytes), mime_type).await?;
+            job.image_task_id = Some(task_id.clone());
+            save_job(app, job)?;
+            task_id
+        }
+    };
+
+    // Keep base GLB URL for fallback when rigging/animation fails.
+    // Re-polling an already finished task returns immediately with a fresh URL.
+    let base_glb_url = providers.mesh.wait_for_mesh(progress, &task_id).await?;
+
+    // Step 4-6: Try rig+animation first; if unsupported/failed, fallback to static base model.
+    let rigged_model_paths = match &job.rigging_error {
+        Some(err) => Err(AppError::new(ErrorCode::TaskFailed, err.clone()).with_provider(Provider::Meshy)),
+        None => rig_and_animate(app, &providers, progress, job, &task_id).await,
+    };
+
+    let (idle_path, attack_path) = match rigged_model_paths {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("Rigging/animation pipeline failed for task {}: {}", task_id, err);
+            progress.rigging_fallback(&err.message);
+            if job.rigging_error.is_none() {
+                job.rigging_error = Some(err.message);
+                save_job(app, job)?;
+            }
+            progress.stage(PipelineStage::Downloading);
+
+            let fallback_bytes = providers.mesh.download_model(&base_glb_url).await?;
+            let fallback_path = app.state::<AssetStore>().put(&job.id, &fallback_bytes, "glb")?;
+            (fallback_path.clone(), fallback_path)
+        }
+    };
+
+    // Step 7: save the robot
+    progress.stage(PipelineStage::Saving);
+    let finished_at_ms = now_ms();
+    let new_robot = db::RobotRecord {
+        // The job's assets already live in the folder named after its id.
+        id: job.id.clone(),
+        name: stats.name,
+        lore: stats.lore,
+        hp: stats.hp,
+        atk: stats.atk,
+        def: stats.def,
+        original_image_path: job.original_image_path.clone(),
+        image_path: generated_image_path,
+        model_path: idle_path,
+        attack_model_path: attack_path,
+        created_at: finished_at_ms / 1000,
+        generation_time_ms: finished_at_ms - job.created_at_ms,
+        is_favorite: false,
+        stats_adjusted: !stats.adjustments.is_empty(),
+        stat_adjustments: stats.adjustments,
+        nutrition: stats.nutrition,
+        stat_formula_version: stats.formula_version,
+        perceptual_hash: job.perceptual_hash.clone(),
+    };
+
+    job.status = db::JobStatus::Completed;
+    job.robot_id = Some(new_robot.id.clone());
+    job.updated_at_ms = finished_at_ms;
+
+    let state = app.state::<Mutex<rusqlite::Connection>>();
+    let mut conn = state.lock()?;
+    db::complete_job(&mut conn, job, &new_robot)?;
+
+    Ok(new_robot)
+}
+
+async fn rig_and_animate(
+    app: &AppHandle,
+    providers: &Providers,
+    progress: &ProgressReporter,
+    job: &mut db::GenerationJob,
+    task_id: &str,
+) -> AppResult<(String, String)> {
+    progress.stage(PipelineStage::Rigging);
+    let rig_task_id = match &job.rig_task_id {
+        Some(rig_task_id) => rig_task_id.clone(),
+        None => {
+            let rig_task_id = providers.mesh.create_rig_task(task_id).await?;
+            job.rig_task_id = Some(rig_task_id.clone());
+            save_job(app, job)?;
+            rig_task_id
+        }
+    };
+    providers.mesh.wait_for_rig(progress, &rig_task_id).await?;
+
+    let actions = app.state::<Arc<SettingsStore>>().get().meshy;
+    progress.stage(PipelineStage::Animating);
+    let idle_anim_task_id = match &job.idle_anim_task_id {
+        Some(id) => id.clone(),
+        None => {
+            let id = providers.mesh.create_animation_task(&rig_task_id, actions.idle_action_id).await?;
+            job.idle_anim_task_id = Some(id.clone());
+            save_job(app, job)?;
+            id
+        }
+    };
+    let attack_anim_task_id = match &job.attack_anim_task_id {
+        Some(id) => id.clone(),
+        None => {
+            let id = providers.mesh.create_animation_task(&rig_task_id, actions.attack_action_id).await?;
+            job.attack_anim_task_id = Some(id.clone());
+            save_job(app, job)?;
+            id
+        }
+    };
+
+    let (idle_url_res, attack_url_res) = tokio::join!(
+        providers.mesh.wait_for_animation(progress, &idle_anim_task_id, "idle"),
+        providers.mesh.wait_for_animation(progress, &attack_anim_task_id, "attack")
+    );
+
+    let idle_url = idle_url_res?;
+    let attack_url = attack_url_res?;
+
+    progress.stage(PipelineStage::Downloading);
+    let assets = app.state::<AssetStore>();
+    let idle_bytes = providers.mesh.download_model(&idle_url).await?;
+    let idle_path = assets.put(&job.id, &idle_bytes, "glb")?;
+    let attack_bytes = providers.mesh.download_model(&attack_url).await?;
+    let attack_path = assets.put(&job.id, &attack_bytes, "glb")?;
+    Ok((idle_path, attack_path))
+}
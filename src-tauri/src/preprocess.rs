@@ -0,0 +1,248 @@
+//! Normalizes uploaded food photos before anything else sees them: sniffs the
+//! real format (the file extension and data URI are not trusted), applies the
+//! EXIF orientation, downscales, and re-encodes as PNG or JPEG.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::{imageops::FilterType, DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+
+use crate::error::{AppError, AppResult};
+
+/// Longest edge sent to the providers. Larger photos only cost upload time
+/// and tokens without improving the food analysis.
+pub const MAX_DIMENSION: u32 = 1536;
+
+const JPEG_QUALITY: u8 = 90;
+
+/// ISO-BMFF major brands of HEVC-coded HEIC files, which `image` cannot decode.
+/// The generic HEIF brands (`mif1`, `msf1`) are left out: AVIF files use them
+/// too and should get the generic "not supported" message instead.
+const HEIC_BRANDS: &[&[u8; 4]] = &[b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis"];
+
+/// An upload ready to be stored and sent to a provider.
+#[derive(Debug, Clone)]
+pub struct PreparedImage {
+    pub bytes: Vec<u8>,
+    pub format: ImageFormat,
+    /// Hex SHA-256 of `bytes`.
+    pub content_hash: String,
+    /// 64-bit difference hash as 16 hex digits; see `perceptual_hash`.
+    pub perceptual_hash: String,
+}
+
+impl PreparedImage {
+    pub fn mime_type(&self) -> &'static str {
+        self.format.to_mime_type()
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.bytes)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        extension(self.format)
+    }
+}
+
+/// File extension the asset store uses for a supported format.
+pub fn extension(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::WebP => "webp",
+        _ => "png",
+    }
+}
+
+fn is_heic(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && HEIC_BRANDS.iter().any(|brand| &bytes[8..12] == *brand)
+}
+
+/// Detect the format from the file's contents.
+pub fn sniff_format(bytes: &[u8]) -> AppResult<ImageFormat> {
+    if is_heic(bytes) {
+        return Err(AppError::invalid_input(
+            "HEIC photos are not supported yet. Export the photo as JPEG or PNG and try again.",
+        ));
+    }
+    match image::guess_format(bytes) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => Ok(format),
+        Ok(format) => Err(AppError::invalid_input(format!(
+            "{:?} images are not supported. Use a PNG, JPEG or WebP photo.",
+            format
+        ))),
+        Err(_) => Err(AppError::invalid_input("The uploaded file is not a recognizable image")),
+    }
+}
+
+/// MIME type of already prepared image bytes, for sending them to a provider.
+pub fn mime_type(bytes: &[u8]) -> &'static str {
+    sniff_format(bytes).map(|f| f.to_mime_type()).unwrap_or("image/png")
+}
+
+fn decode(bytes: &[u8], format: ImageFormat) -> AppResult<DynamicImage> {
+    let invalid = |e: image::ImageError| AppError::invalid_input(format!("Failed to decode image: {}", e));
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .map_err(invalid)?;
+    // A broken EXIF block shouldn't reject an otherwise readable photo.
+    let orientation = decoder.orientation().ok();
+    let mut image = DynamicImage::from_decoder(decoder).map_err(invalid)?;
+    if let Some(orientation) = orientation {
+        image.apply_orientation(orientation);
+    }
+    Ok(image)
+}
+
+/// Photos without transparency become JPEG; anything with an alpha channel stays PNG.
+fn encode(image: &DynamicImage) -> AppResult<(Vec<u8>, ImageFormat)> {
+    let mut out = Cursor::new(Vec::new());
+    let format = if image.color().has_alpha() {
+        image.write_to(&mut out, ImageFormat::Png)
+            .map_err(|e| AppError::internal(format!("Failed to encode PNG: {}", e)))?;
+        ImageFormat::Png
+    } else {
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+        image.to_rgb8().write_with_encoder(encoder)
+            .map_err(|e| AppError::internal(format!("Failed to encode JPEG: {}", e)))?;
+        ImageFormat::Jpeg
+    };
+    Ok((out.into_inner(), format))
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Difference hash (dHash): shrink to 9x8 grayscale and record whether each
+/// pixel is brighter than its right neighbour. Re-encoded, resized or slightly
+/// recompressed copies of a photo land within a few bits of each other.
+pub fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Number of differing bits between two hashes from `PreparedImage::perceptual_hash`,
+/// or `None` if either is malformed.
+pub fn hash_distance(a: &str, b: &str) -> Option<u32> {
+    let a = u64::from_str_radix(a, 16).ok()?;
+    let b = u64::from_str_radix(b, 16).ok()?;
+    Some((a ^ b).count_ones())
+}
+
+/// Decode an upload sent from the frontend as base64, with or without a
+/// `data:image/...;base64,` prefix. The prefix's MIME type is ignored.
+pub fn decode_base64(data: &str) -> AppResult<Vec<u8>> {
+    let clean = data.rsplit(',').next().unwrap_or("");
+    STANDARD
+        .decode(clean.trim())
+        .map_err(|e| AppError::invalid_input(format!("Invalid base64 image: {}", e)))
+}
+
+/// Run the full preprocessing stage on raw upload bytes.
+pub fn prepare(bytes: &[u8]) -> AppResult<PreparedImage> {
+    let format = sniff_format(bytes)?;
+    let mut image = decode(bytes, format)?;
+    let perceptual_hash = format!("{:016x}", perceptual_hash(&image));
+    if image.width().max(image.height()) > MAX_DIMENSION {
+        image = image.resize(MAX_DIMENSION, MAX_DIMENSION, FilterType::Lanczos3);
+    }
+    let (bytes, format) = encode(&image)?;
+    Ok(PreparedImage {
+        content_hash: content_hash(&bytes),
+        perceptual_hash,
+        bytes,
+        format,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::DUPLICATE_MAX_DISTANCE;
+    use image::{Rgb, RgbImage};
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x * 255 / width) as u8, (y * 255 / height) as u8, 128])
+        }))
+    }
+
+    #[test]
+    fn hash_distance_counts_differing_bits() {
+        assert_eq!(hash_distance("0000000000000000", "0000000000000000"), Some(0));
+        assert_eq!(hash_distance("0000000000000000", "000000000000000f"), Some(4));
+        assert_eq!(hash_distance("ffffffffffffffff", "0000000000000000"), Some(64));
+        assert_eq!(hash_distance("not hex", "0000000000000000"), None);
+    }
+
+    /// Light and dark squares, `cells` per side. Unlike a gradient, neighbouring
+    /// samples compare both ways, so the hash has bits of both values.
+    fn checkerboard(width: u32, height: u32, cells: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let light = (x * cells / width + y * cells / height).is_multiple_of(2);
+            if light { Rgb([230, 220, 200]) } else { Rgb([30, 40, 50]) }
+        }))
+    }
+
+    fn hash_hex(image: &DynamicImage) -> String {
+        format!("{:016x}", perceptual_hash(image))
+    }
+
+    #[test]
+    fn resized_copy_keeps_a_close_perceptual_hash() {
+        let original = checkerboard(800, 600, 4);
+        let hash = perceptual_hash(&original);
+        assert!(hash != 0 && hash != u64::MAX);
+
+        let resized = original.resize(200, 150, FilterType::Triangle);
+        let distance = hash_distance(&hash_hex(&original), &hash_hex(&resized)).unwrap();
+        assert!(distance <= DUPLICATE_MAX_DISTANCE);
+
+        let different = checkerboard(800, 600, 3);
+        let distance = hash_distance(&hash_hex(&original), &hash_hex(&different)).unwrap();
+        assert!(distance > DUPLICATE_MAX_DISTANCE, "distance {}", distance);
+    }
+
+    #[test]
+    fn prepare_downscales_and_reencodes() {
+        let mut png = Cursor::new(Vec::new());
+        gradient(3000, 1000).write_to(&mut png, ImageFormat::Png).unwrap();
+        let prepared = prepare(png.get_ref()).unwrap();
+        assert_eq!(prepared.format, ImageFormat::Jpeg);
+        let decoded = image::load_from_memory(&prepared.bytes).unwrap();
+        assert_eq!(decoded.width(), MAX_DIMENSION);
+        assert_eq!(prepared.content_hash, content_hash(&prepared.bytes));
+    }
+
+    fn ftyp(brand: &[u8; 4]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0, 24];
+        bytes.extend_from_slice(b"ftyp");
+        bytes.extend_from_slice(brand);
+        bytes.resize(64, 0);
+        bytes
+    }
+
+    #[test]
+    fn rejects_heic() {
+        let err = sniff_format(&ftyp(b"heic")).unwrap_err();
+        assert!(err.message.contains("HEIC"), "{}", err.message);
+    }
+
+    #[test]
+    fn avif_is_not_reported_as_heic() {
+        for brand in [b"avif", b"mif1"] {
+            let err = sniff_format(&ftyp(brand)).unwrap_err();
+            assert!(!err.message.contains("HEIC"), "{}", err.message);
+        }
+    }
+}
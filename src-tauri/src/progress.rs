@@ -0,0 +1,311 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+use crate::clock::now_ms;
+use crate::error::AppError;
+
+/// Pipeline stages in execution order. The UI localizes these; the backend no
+/// longer sends display strings.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    Queued,
+    AnalyzingFood,
+    GeneratingImage,
+    GeneratingMesh,
+    Rigging,
+    Animating,
+    Downloading,
+    Saving,
+}
+
+impl PipelineStage {
+    const ORDER: [PipelineStage; 8] = [
+        PipelineStage::Queued,
+        PipelineStage::AnalyzingFood,
+        PipelineStage::GeneratingImage,
+        PipelineStage::GeneratingMesh,
+        PipelineStage::Rigging,
+        PipelineStage::Animating,
+        PipelineStage::Downloading,
+        PipelineStage::Saving,
+    ];
+
+    /// Share of the overall progress bar, roughly proportional to typical
+    /// wall-clock time. Weights sum to 100.
+    fn weight(self) -> f64 {
+        match self {
+            PipelineStage::Queued => 0.0,
+            PipelineStage::AnalyzingFood => 5.0,
+            PipelineStage::GeneratingImage => 10.0,
+            PipelineStage::GeneratingMesh => 40.0,
+            PipelineStage::Rigging => 20.0,
+            PipelineStage::Animating => 20.0,
+            PipelineStage::Downloading => 4.0,
+            PipelineStage::Saving => 1.0,
+        }
+    }
+
+    /// Overall percent reached once every stage before `self` has finished.
+    fn overall_before(self) -> f64 {
+        Self::ORDER
+            .iter()
+            .take_while(|s| **s != self)
+            .map(|s| s.weight())
+            .sum()
+    }
+}
+
+/// Payload of every `pipeline-progress` event.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PipelineEvent {
+    Progress {
+        job_id: String,
+        stage: PipelineStage,
+        /// Sub-task within the stage, e.g. `idle` or `attack` while animating.
+        detail: Option<String>,
+        stage_percent: u32,
+        overall_percent: u32,
+        elapsed_ms: u64,
+        eta_ms: Option<u64>,
+    },
+    /// Rigging or animation failed; the job continues with the static base model.
+    RiggingFallback {
+        job_id: String,
+        reason: String,
+        overall_percent: u32,
+        elapsed_ms: u64,
+    },
+    Completed {
+        job_id: String,
+        robot_id: String,
+        elapsed_ms: u64,
+    },
+    Failed {
+        job_id: String,
+        error: AppError,
+        elapsed_ms: u64,
+    },
+    Cancelled {
+        job_id: String,
+        elapsed_ms: u64,
+    },
+}
+
+struct ProgressState {
+    stage: PipelineStage,
+    subtask_percents: HashMap<String, u32>,
+    overall: f64,
+    /// Start of the current run and the overall percent it started from, so a
+    /// resumed job estimates its ETA from the rate of this run only.
+    run_started: Instant,
+    run_baseline: f64,
+}
+
+impl ProgressState {
+    fn new() -> Self {
+        Self {
+            stage: PipelineStage::Queued,
+            subtask_percents: HashMap::new(),
+            overall: 0.0,
+            run_started: Instant::now(),
+            run_baseline: 0.0,
+        }
+    }
+
+    fn enter(&mut self, stage: PipelineStage) {
+        if self.stage == PipelineStage::Queued && stage != PipelineStage::Queued {
+            // First real work of this run: start measuring the rate for the ETA here.
+            self.run_started = Instant::now();
+            self.run_baseline = stage.overall_before();
+        }
+        self.stage = stage;
+        self.subtask_percents.clear();
+    }
+
+    /// Record `percent` for `stage` (or one of its sub-tasks) and return the
+    /// stage percent, the overall percent and the ETA.
+    fn update(&mut self, stage: PipelineStage, subtask: Option<&str>, percent: u32) -> (u32, f64, Option<u64>) {
+        let percent = percent.min(100);
+        let stage_percent = match subtask {
+            Some(name) => {
+                self.subtask_percents.insert(name.to_string(), percent);
+                let sum: u32 = self.subtask_percents.values().sum();
+                sum / self.subtask_percents.len() as u32
+            }
+            None => percent,
+        };
+
+        // Never move the bar backwards, e.g. when a resumed task re-reports progress.
+        let overall = (stage.overall_before() + stage.weight() * stage_percent as f64 / 100.0).max(self.overall);
+        self.overall = overall;
+
+        let done_this_run = overall - self.run_baseline;
+        let run_elapsed = self.run_started.elapsed().as_millis() as f64;
+        let eta_ms = if done_this_run >= 1.0 {
+            Some((run_elapsed / done_this_run * (100.0 - overall)) as u64)
+        } else {
+            None
+        };
+
+        (stage_percent, overall, eta_ms)
+    }
+}
+
+/// Emits `pipeline-progress` events tagged with the job they belong to, so the
+/// UI can tell concurrent jobs apart.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    app: AppHandle,
+    job_id: String,
+    job_created_at_ms: i64,
+    state: Arc<Mutex<ProgressState>>,
+}
+
+impl ProgressReporter {
+    pub fn new(app: &AppHandle, job_id: &str, job_created_at_ms: i64) -> Self {
+        Self {
+            app: app.clone(),
+            job_id: job_id.to_string(),
+            job_created_at_ms,
+            state: Arc::new(Mutex::new(ProgressState::new())),
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        (now_ms() - self.job_created_at_ms).max(0) as u64
+    }
+
+    fn emit(&self, event: PipelineEvent) {
+        let _ = self.app.emit("pipeline-progress", event);
+    }
+
+    /// Enter `stage` at 0%. Entering a stage out of order (e.g. skipping
+    /// rigging for the fallback) simply jumps the overall percent.
+    pub fn stage(&self, stage: PipelineStage) {
+        if let Ok(mut state) = self.state.lock() {
+            state.enter(stage);
+        }
+        self.report(stage, None, 0);
+    }
+
+    pub fn stage_progress(&self, stage: PipelineStage, percent: u32) {
+        self.report(stage, None, percent);
+    }
+
+    /// Progress of one of several parallel sub-tasks; the stage percent is their average.
+    pub fn subtask_progress(&self, stage: PipelineStage, subtask: &str, percent: u32) {
+        self.report(stage, Some(subtask), percent);
+    }
+
+    fn report(&self, stage: PipelineStage, subtask: Option<&str>, percent: u32) {
+        let (stage_percent, overall, eta_ms) = match self.state.lock() {
+            Ok(mut state) => state.update(stage, subtask, percent),
+            Err(_) => return,
+        };
+
+        self.emit(PipelineEvent::Progress {
+            job_id: self.job_id.clone(),
+            stage,
+            detail: subtask.map(str::to_string),
+            stage_percent,
+            overall_percent: overall.round() as u32,
+            elapsed_ms: self.elapsed_ms(),
+            eta_ms,
+        });
+    }
+
+    fn overall_percent(&self) -> u32 {
+        self.state.lock().map(|s| s.overall.round() as u32).unwrap_or(0)
+    }
+
+    pub fn rigging_fallback(&self, reason: &str) {
+        self.emit(PipelineEvent::RiggingFallback {
+            job_id: self.job_id.clone(),
+            reason: reason.to_string(),
+            overall_percent: self.overall_percent(),
+            elapsed_ms: self.elapsed_ms(),
+        });
+    }
+
+    pub fn completed(&self, robot_id: &str) {
+        self.emit(PipelineEvent::Completed {
+            job_id: self.job_id.clone(),
+            robot_id: robot_id.to_string(),
+            elapsed_ms: self.elapsed_ms(),
+        });
+    }
+
+    pub fn failed(&self, error: &AppError) {
+        self.emit(PipelineEvent::Failed {
+            job_id: self.job_id.clone(),
+            error: error.clone(),
+            elapsed_ms: self.elapsed_ms(),
+        });
+    }
+
+    pub fn cancelled(&self) {
+        self.emit(PipelineEvent::Cancelled {
+            job_id: self.job_id.clone(),
+            elapsed_ms: self.elapsed_ms(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_weights_sum_to_100() {
+        let total: f64 = PipelineStage::ORDER.iter().map(|s| s.weight()).sum();
+        assert_eq!(total, 100.0);
+    }
+
+    #[test]
+    fn overall_percent_never_decreases() {
+        let mut state = ProgressState::new();
+        let mut last = 0.0;
+        for stage in PipelineStage::ORDER {
+            state.enter(stage);
+            for percent in [0, 50, 20, 100] {
+                let (_, overall, _) = state.update(stage, None, percent);
+                assert!(overall >= last, "{:?} at {}% went back to {}", stage, percent, overall);
+                last = overall;
+            }
+        }
+        assert_eq!(last, 100.0);
+
+        // Skipping rigging and animation for the fallback jumps ahead; re-entering
+        // an earlier stage afterwards must not pull the bar back.
+        state.enter(PipelineStage::GeneratingMesh);
+        let (_, overall, _) = state.update(PipelineStage::GeneratingMesh, None, 10);
+        assert_eq!(overall, 100.0);
+    }
+
+    #[test]
+    fn subtask_percents_are_averaged() {
+        let mut state = ProgressState::new();
+        state.enter(PipelineStage::Animating);
+        state.update(PipelineStage::Animating, Some("idle"), 100);
+        let (stage_percent, _, _) = state.update(PipelineStage::Animating, Some("attack"), 0);
+        assert_eq!(stage_percent, 50);
+    }
+
+    #[test]
+    fn eta_is_unknown_until_progress_is_made() {
+        let mut state = ProgressState::new();
+        assert_eq!(state.update(PipelineStage::Queued, None, 0).2, None);
+
+        // A resumed job starts its run at the mesh stage; nothing done yet this run.
+        state.enter(PipelineStage::GeneratingMesh);
+        assert_eq!(state.update(PipelineStage::GeneratingMesh, None, 0).2, None);
+
+        let (_, _, eta) = state.update(PipelineStage::GeneratingMesh, None, 50);
+        assert!(eta.is_some());
+    }
+}
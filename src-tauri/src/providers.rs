@@ -0,0 +1,101 @@
+//! Extension points for the generation pipeline. `run_generation_pipeline`
+//! only talks to these traits, so vendors can be swapped (or faked) without
+//! touching the pipeline itself.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use std::env;
+use std::sync::Arc;
+
+use crate::error::AppResult;
+use crate::gemini::{GeminiProvider, RobotStatus};
+use crate::keys::ApiKeyStore;
+use crate::settings::SettingsStore;
+use crate::meshy::MeshyProvider;
+use crate::mock::{MockConfig, MockProvider};
+use crate::progress::ProgressReporter;
+
+/// Turns a food photo into robot stats, lore and a visual description.
+#[async_trait]
+pub trait StatsProvider: Send + Sync {
+    /// `mime_type` is the real type of the decoded image, see `preprocess`.
+    async fn generate_stats(&self, base64_image: String, mime_type: &str) -> AppResult<RobotStatus>;
+}
+
+/// Renders the robot concept art from a visual description. Returns base64 image data, usually PNG.
+#[async_trait]
+pub trait ConceptImageProvider: Send + Sync {
+    async fn generate_image(&self, prompt: String) -> AppResult<String>;
+}
+
+/// Kinds of remote task a `MeshProvider` runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshTaskKind {
+    ImageTo3d,
+    Rigging,
+    Animation,
+}
+
+/// Image-to-3D generation plus optional rigging and animation.
+#[async_trait]
+pub trait MeshProvider: Send + Sync {
+    /// Submit an image-to-3D task for base64 image data and return its task id.
+    /// `mime_type` is the real type of the decoded image, see `preprocess`.
+    async fn create_mesh_task(&self, base64_image: String, mime_type: &str) -> AppResult<String>;
+    /// Wait for the mesh task and return the URL of the static GLB.
+    async fn wait_for_mesh(&self, progress: &ProgressReporter, task_id: &str) -> AppResult<String>;
+    async fn create_rig_task(&self, mesh_task_id: &str) -> AppResult<String>;
+    async fn wait_for_rig(&self, progress: &ProgressReporter, rig_task_id: &str) -> AppResult<()>;
+    async fn create_animation_task(&self, rig_task_id: &str, action_id: u32) -> AppResult<String>;
+    /// Wait for an animation task and return the URL of the animated GLB.
+    /// `name` identifies the animation in progress events.
+    async fn wait_for_animation(
+        &self,
+        progress: &ProgressReporter,
+        task_id: &str,
+        name: &str,
+    ) -> AppResult<String>;
+    /// Stop a remote task where the vendor supports it.
+    async fn cancel_task(&self, kind: MeshTaskKind, task_id: &str) -> AppResult<()>;
+    /// Fetch a model returned by one of the `wait_for_*` methods.
+    async fn download_model(&self, url: &str) -> AppResult<Vec<u8>>;
+}
+
+/// The provider set used by the pipeline, held in Tauri state.
+#[derive(Clone)]
+pub struct Providers {
+    pub stats: Arc<dyn StatsProvider>,
+    pub image: Arc<dyn ConceptImageProvider>,
+    pub mesh: Arc<dyn MeshProvider>,
+}
+
+impl Providers {
+    /// Gemini for stats and concept art, Meshy for 3D, sharing one HTTP
+    /// client, key store and settings.
+    pub fn remote(client: Client, keys: Arc<ApiKeyStore>, settings: Arc<SettingsStore>) -> Self {
+        let gemini = Arc::new(GeminiProvider::from_env(client.clone(), keys.clone(), settings.clone()));
+        Self {
+            stats: gemini.clone(),
+            image: gemini,
+            mesh: Arc::new(MeshyProvider::from_env(client, keys, settings)),
+        }
+    }
+
+    /// Offline mock for all three roles. See `mock::MockConfig::from_env`.
+    pub fn mock(config: MockConfig) -> Self {
+        let mock = Arc::new(MockProvider::new(config));
+        Self {
+            stats: mock.clone(),
+            image: mock.clone(),
+            mesh: mock,
+        }
+    }
+
+    /// `FFR_PROVIDER=mock` selects the offline mock; anything else uses the remote providers.
+    pub fn from_env(client: Client, keys: Arc<ApiKeyStore>, settings: Arc<SettingsStore>) -> Self {
+        match env::var("FFR_PROVIDER").as_deref() {
+            Ok("mock") => Self::mock(MockConfig::from_env()),
+            _ => Self::remote(client, keys, settings),
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! User-tunable generation settings, persisted as `settings.json` in the app
+//! data directory. Every field has a default, so older files and partial
+//! updates simply fill in what they lack.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::error::{AppError, AppResult};
+use crate::stats::OutOfRangePolicy;
+
+/// Bump when a field changes meaning, and teach `migrate` the old layout.
+pub const SETTINGS_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GeminiSettings {
+    /// Model that analyzes the food photo.
+    pub stats_model: String,
+    /// Model that draws the robot concept art.
+    pub image_model: String,
+}
+
+impl Default for GeminiSettings {
+    fn default() -> Self {
+        Self {
+            stats_model: "gemini-2.5-flash".to_string(),
+            image_model: "nano-banana-pro-preview".to_string(),
+        }
+    }
+}
+
+/// Upper bounds for the polling settings. Beyond these a job would stall for
+/// hours, and `Duration` arithmetic in the poll loop could overflow.
+pub const MAX_POLL_DELAY_MS: u64 = 5 * 60 * 1000;
+pub const MAX_BACKOFF_MULTIPLIER: f64 = 10.0;
+pub const MAX_POLL_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PollingSettings {
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub backoff_multiplier: f64,
+    pub mesh_timeout_secs: u64,
+    pub rigging_timeout_secs: u64,
+    pub animation_timeout_secs: u64,
+}
+
+impl Default for PollingSettings {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 2_000,
+            max_delay_ms: 15_000,
+            backoff_multiplier: 1.5,
+            mesh_timeout_secs: 10 * 60,
+            rigging_timeout_secs: 20 * 60,
+            animation_timeout_secs: 20 * 60,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MeshySettings {
+    pub enable_pbr: bool,
+    /// Meshy animation library ids.
+    pub idle_action_id: u32,
+    pub attack_action_id: u32,
+    pub polling: PollingSettings,
+}
+
+impl Default for MeshySettings {
+    fn default() -> Self {
+        Self {
+            enable_pbr: true,
+            idle_action_id: 0,
+            attack_action_id: 92,
+            polling: PollingSettings::default(),
+        }
+    }
+}
+
+/// Inclusive bounds for one stat.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct StatBounds {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl StatBounds {
+    /// Stand-in when the model did not provide the stat at all.
+    pub fn midpoint(self) -> i32 {
+        self.min + (self.max - self.min) / 2
+    }
+}
+
+/// Nutrient amounts that map to a stat's minimum and maximum.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct NutrientRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Tunable inputs of `stats::apply_formula`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct StatFormula {
+    /// kcal mapped onto HP.
+    pub calories: NutrientRange,
+    /// Grams of protein mapped onto ATK.
+    pub protein: NutrientRange,
+    /// Grams of fiber mapped onto DEF.
+    pub fiber: NutrientRange,
+}
+
+impl Default for StatFormula {
+    fn default() -> Self {
+        Self {
+            calories: NutrientRange { min: 0.0, max: 1500.0 },
+            protein: NutrientRange { min: 0.0, max: 60.0 },
+            fiber: NutrientRange { min: 0.0, max: 15.0 },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct StatRanges {
+    pub hp: StatBounds,
+    pub atk: StatBounds,
+    pub def: StatBounds,
+    pub out_of_range: OutOfRangePolicy,
+    pub formula: StatFormula,
+}
+
+impl Default for StatRanges {
+    fn default() -> Self {
+        Self {
+            hp: StatBounds { min: 500, max: 2000 },
+            atk: StatBounds { min: 10, max: 100 },
+            def: StatBounds { min: 5, max: 50 },
+            out_of_range: OutOfRangePolicy::Clamp,
+            formula: StatFormula::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub gemini: GeminiSettings,
+    pub meshy: MeshySettings,
+    pub stats: StatRanges,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            gemini: GeminiSettings::default(),
+            meshy: MeshySettings::default(),
+            stats: StatRanges::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn validate(&self) -> AppResult<()> {
+        if self.gemini.stats_model.trim().is_empty() || self.gemini.image_model.trim().is_empty() {
+            return Err(AppError::invalid_input("Model names must not be empty"));
+        }
+
+        let polling = &self.meshy.polling;
+        if polling.initial_delay_ms == 0
+            || polling.initial_delay_ms > polling.max_delay_ms
+            || polling.max_delay_ms > MAX_POLL_DELAY_MS
+        {
+            return Err(AppError::invalid_input(format!(
+                "Polling delay must be positive, no larger than the maximum delay, and at most {} ms",
+                MAX_POLL_DELAY_MS
+            )));
+        }
+        if !(1.0..=MAX_BACKOFF_MULTIPLIER).contains(&polling.backoff_multiplier) {
+            return Err(AppError::invalid_input(format!(
+                "Backoff multiplier must be between 1 and {}",
+                MAX_BACKOFF_MULTIPLIER
+            )));
+        }
+        let timeouts = [
+            polling.mesh_timeout_secs,
+            polling.rigging_timeout_secs,
+            polling.animation_timeout_secs,
+        ];
+        if timeouts.iter().any(|&t| t == 0 || t > MAX_POLL_TIMEOUT_SECS) {
+            return Err(AppError::invalid_input(format!(
+                "Polling timeouts must be between 1 and {} seconds",
+                MAX_POLL_TIMEOUT_SECS
+            )));
+        }
+
+        for (name, bounds) in [("HP", self.stats.hp), ("ATK", self.stats.atk), ("DEF", self.stats.def)] {
+            if bounds.min < 0 || bounds.min > bounds.max {
+                return Err(AppError::invalid_input(format!(
+                    "{} range {}-{} is invalid",
+                    name, bounds.min, bounds.max
+                )));
+            }
+        }
+
+        let formula = &self.stats.formula;
+        for (name, range) in [
+            ("Calories", formula.calories),
+            ("Protein", formula.protein),
+            ("Fiber", formula.fiber),
+        ] {
+            if !(range.min >= 0.0 && range.min < range.max && range.max.is_finite()) {
+                return Err(AppError::invalid_input(format!(
+                    "{} formula range {}-{} is invalid",
+                    name, range.min, range.max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Upgrade a settings file written by an older version. Fields the old
+/// layout lacked fall back to their defaults during deserialization; a file
+/// from a newer build is rejected rather than silently misread.
+fn migrate(mut value: serde_json::Value) -> AppResult<serde_json::Value> {
+    let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if version > SETTINGS_VERSION as u64 {
+        return Err(AppError::invalid_input(format!(
+            "Settings file is v{} but this build understands up to v{}",
+            version, SETTINGS_VERSION
+        )));
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), SETTINGS_VERSION.into());
+    }
+    Ok(value)
+}
+
+/// The settings file plus an in-memory copy, held in Tauri state.
+pub struct SettingsStore {
+    path: PathBuf,
+    current: RwLock<Settings>,
+}
+
+impl SettingsStore {
+    /// Load `settings.json` from `dir`, falling back to defaults when it is
+    /// missing or unreadable. A broken file is left untouched until the user
+    /// saves new settings.
+    pub fn open(dir: &Path) -> Self {
+        let path = dir.join("settings.json");
+        let current = match Self::load(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Failed to read {}, using defaults: {}", path.display(), e);
+                Settings::default()
+            }
+        };
+        Self {
+            path,
+            current: RwLock::new(current),
+        }
+    }
+
+    fn load(path: &Path) -> AppResult<Settings> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let value: serde_json::Value = serde_json::from_str(&text)?;
+        let settings: Settings = serde_json::from_value(migrate(value)?)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Snapshot of the current settings. Callers read this per job, so
+    /// changes apply to the next generation without a restart.
+    pub fn get(&self) -> Settings {
+        self.current
+            .read()
+            .map(|s| s.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+
+    pub fn update(&self, mut settings: Settings) -> AppResult<Settings> {
+        settings.validate()?;
+        settings.version = SETTINGS_VERSION;
+
+        let mut current = self.current.write()?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&settings)?)?;
+        fs::rename(&tmp, &self.path)?;
+        *current = settings.clone();
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        Settings::default().validate().unwrap();
+    }
+
+    #[test]
+    fn rejects_polling_values_that_would_overflow() {
+        let mut settings = Settings::default();
+        settings.meshy.polling.backoff_multiplier = 1e300;
+        assert!(settings.validate().is_err());
+
+        let mut settings = Settings::default();
+        settings.meshy.polling.max_delay_ms = u64::MAX;
+        assert!(settings.validate().is_err());
+
+        let mut settings = Settings::default();
+        settings.meshy.polling.rigging_timeout_secs = u64::MAX;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn migrate_fills_missing_fields_and_stamps_version() {
+        let old = serde_json::json!({ "meshy": { "polling": { "max_delay_ms": 30_000 } } });
+        let settings: Settings = serde_json::from_value(migrate(old).unwrap()).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.meshy.polling.max_delay_ms, 30_000);
+        assert_eq!(settings.meshy.polling.initial_delay_ms, PollingSettings::default().initial_delay_ms);
+        assert_eq!(settings.gemini.stats_model, GeminiSettings::default().stats_model);
+    }
+
+    #[test]
+    fn migrate_rejects_newer_versions() {
+        let newer = serde_json::json!({ "version": SETTINGS_VERSION + 1 });
+        assert!(migrate(newer).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Robot stats are computed from the model's nutrition estimate by a fixed,
+//! versioned formula, so the same food always gives the same robot. Every
+//! `StatsProvider` result passes through `finalize` before it is saved.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::gemini::RobotStatus;
+use crate::settings::{NutrientRange, StatBounds, StatRanges};
+
+/// Version of the nutrition-to-stats formula implemented by `apply_formula`.
+/// Bump it whenever the shape of the formula changes (not for tuning the
+/// configurable ranges), so robots can be recalibrated by version.
+pub const FORMULA_VERSION: u32 = 1;
+
+/// The model's estimate for the photographed food, kept so users can see why
+/// a robot got its stats and so the ranges can be recalibrated later.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Nutrition {
+    #[serde(default, alias = "FoodName", alias = "foodName")]
+    pub food_name: Option<String>,
+    /// kcal for the portion in the photo.
+    #[serde(default, alias = "Calories", alias = "calories_kcal")]
+    pub calories: Option<f64>,
+    /// Grams of protein.
+    #[serde(default, alias = "Protein", alias = "protein_g")]
+    pub protein: Option<f64>,
+    /// Grams of dietary fiber.
+    #[serde(default, alias = "Fiber", alias = "fiber_g")]
+    pub fiber: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentKind {
+    /// The model omitted the field (or sent something unparseable), so a default was used.
+    Defaulted,
+    /// The value was outside the configured range and was clamped into it.
+    Clamped,
+}
+
+/// One correction made to a generated stat block, kept on the robot for auditing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatAdjustment {
+    pub field: String,
+    pub kind: AdjustmentKind,
+    /// Value as returned by the model, for clamped fields.
+    pub original: Option<i32>,
+}
+
+impl StatAdjustment {
+    pub fn defaulted(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            kind: AdjustmentKind::Defaulted,
+            original: None,
+        }
+    }
+}
+
+/// What to do with a nutrient amount outside its formula range, e.g. the
+/// model estimating negative or 50 000 kcal.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfRangePolicy {
+    #[default]
+    Clamp,
+    Reject,
+}
+
+/// Validate one nutrient amount against its formula range. Missing or
+/// non-finite amounts give `None` and are recorded as defaulted; amounts
+/// outside the range are clamped into it (and recorded) or rejected.
+fn check_nutrient(
+    field: &str,
+    amount: Option<f64>,
+    range: NutrientRange,
+    policy: OutOfRangePolicy,
+    adjustments: &mut Vec<StatAdjustment>,
+) -> AppResult<Option<f64>> {
+    let Some(amount) = amount.filter(|a| a.is_finite()) else {
+        adjustments.push(StatAdjustment::defaulted(field));
+        return Ok(None);
+    };
+    if (range.min..=range.max).contains(&amount) {
+        return Ok(Some(amount));
+    }
+    if policy == OutOfRangePolicy::Reject {
+        return Err(AppError::new(
+            ErrorCode::InvalidResponse,
+            format!("Estimated {} {} is outside {}-{}", field, amount, range.min, range.max),
+        ));
+    }
+    adjustments.push(StatAdjustment {
+        field: field.to_string(),
+        kind: AdjustmentKind::Clamped,
+        original: Some(amount.round() as i32),
+    });
+    Ok(Some(amount.clamp(range.min, range.max)))
+}
+
+/// Map `amount`, already within `input`, linearly onto `bounds`.
+fn scale(amount: f64, input: NutrientRange, bounds: StatBounds) -> i32 {
+    let t = (amount - input.min) / (input.max - input.min);
+    bounds.min + (t * (bounds.max - bounds.min) as f64).round() as i32
+}
+
+/// Formula v1: each stat is a linear function of one nutrient.
+///
+/// - HP from calories (kcal)
+/// - ATK from protein (g)
+/// - DEF from dietary fiber (g)
+///
+/// The nutrient amount at `ranges.formula.<nutrient>.min` gives the stat's
+/// minimum and the amount at `.max` its maximum. Amounts outside that range
+/// are handled by `ranges.out_of_range`; a missing amount gives the stat's
+/// midpoint. Both are recorded in `status.adjustments`, so the robot is
+/// flagged as adjusted. The same nutrition estimate always yields the same stats.
+pub fn apply_formula(status: &mut RobotStatus, ranges: &StatRanges) -> AppResult<()> {
+    let formula = &ranges.formula;
+    let policy = ranges.out_of_range;
+    let nutrition = status.nutrition.clone();
+    let mut adjustments = Vec::new();
+    let inputs = [
+        ("calories", nutrition.calories, formula.calories, ranges.hp, &mut status.hp),
+        ("protein", nutrition.protein, formula.protein, ranges.atk, &mut status.atk),
+        ("fiber", nutrition.fiber, formula.fiber, ranges.def, &mut status.def),
+    ];
+    for (field, amount, input, bounds, stat) in inputs {
+        *stat = match check_nutrient(field, amount, input, policy, &mut adjustments)? {
+            Some(amount) => scale(amount, input, bounds),
+            None => bounds.midpoint(),
+        };
+    }
+    status.adjustments.extend(adjustments);
+    status.formula_version = Some(FORMULA_VERSION);
+    Ok(())
+}
+
+/// Bring final stats into `ranges`, whether they came from `apply_formula` or
+/// from an imported package. Returns the clamps made; with
+/// `OutOfRangePolicy::Reject` an out-of-range stat is an error instead.
+pub fn check_stats(
+    hp: &mut i32,
+    atk: &mut i32,
+    def: &mut i32,
+    ranges: &StatRanges,
+) -> AppResult<Vec<StatAdjustment>> {
+    let mut adjustments = Vec::new();
+    for (field, value, bounds) in [("hp", hp, ranges.hp), ("atk", atk, ranges.atk), ("def", def, ranges.def)] {
+        if (bounds.min..=bounds.max).contains(value) {
+            continue;
+        }
+        if ranges.out_of_range == OutOfRangePolicy::Reject {
+            return Err(AppError::invalid_input(format!(
+                "{} {} is outside {}-{}",
+                field.to_uppercase(),
+                value,
+                bounds.min,
+                bounds.max
+            )));
+        }
+        adjustments.push(StatAdjustment {
+            field: field.to_string(),
+            kind: AdjustmentKind::Clamped,
+            original: Some(*value),
+        });
+        *value = (*value).clamp(bounds.min, bounds.max);
+    }
+    Ok(adjustments)
+}
+
+/// Everything a freshly generated `RobotStatus` goes through before it is saved.
+pub fn finalize(status: &mut RobotStatus, ranges: &StatRanges) -> AppResult<()> {
+    apply_formula(status, ranges)?;
+    let adjustments = check_stats(&mut status.hp, &mut status.atk, &mut status.def, ranges)?;
+    status.adjustments.extend(adjustments);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(calories: Option<f64>, protein: Option<f64>, fiber: Option<f64>) -> RobotStatus {
+        RobotStatus {
+            name: "Ramen Titan".to_string(),
+            lore: String::new(),
+            hp: 0,
+            atk: 0,
+            def: 0,
+            visual_description: String::new(),
+            nutrition: Nutrition {
+                food_name: Some("ramen".to_string()),
+                calories,
+                protein,
+                fiber,
+            },
+            adjustments: Vec::new(),
+            formula_version: None,
+        }
+    }
+
+    #[test]
+    fn maps_nutrition_linearly_onto_stats() {
+        let ranges = StatRanges::default();
+        let mut s = status(Some(750.0), Some(60.0), Some(0.0));
+        apply_formula(&mut s, &ranges).unwrap();
+        assert_eq!((s.hp, s.atk, s.def), (1250, 100, 5));
+        assert!(s.adjustments.is_empty());
+        assert_eq!(s.formula_version, Some(FORMULA_VERSION));
+    }
+
+    #[test]
+    fn missing_nutrient_defaults_to_midpoint() {
+        let ranges = StatRanges::default();
+        let mut s = status(None, Some(f64::NAN), Some(15.0));
+        apply_formula(&mut s, &ranges).unwrap();
+        assert_eq!((s.hp, s.atk, s.def), (ranges.hp.midpoint(), ranges.atk.midpoint(), 50));
+        assert_eq!(
+            s.adjustments,
+            [StatAdjustment::defaulted("calories"), StatAdjustment::defaulted("protein")]
+        );
+    }
+
+    #[test]
+    fn out_of_range_nutrient_is_clamped_and_recorded() {
+        let ranges = StatRanges::default();
+        let mut s = status(Some(50_000.0), Some(-5.0), Some(3.0));
+        apply_formula(&mut s, &ranges).unwrap();
+        assert_eq!((s.hp, s.atk), (ranges.hp.max, ranges.atk.min));
+        assert_eq!(s.adjustments.len(), 2);
+        assert_eq!(s.adjustments[0].kind, AdjustmentKind::Clamped);
+        assert_eq!(s.adjustments[0].original, Some(50_000));
+        assert_eq!(s.adjustments[1].field, "protein");
+    }
+
+    #[test]
+    fn out_of_range_nutrient_is_rejected_by_policy() {
+        let ranges = StatRanges {
+            out_of_range: OutOfRangePolicy::Reject,
+            ..StatRanges::default()
+        };
+        let mut s = status(Some(50_000.0), Some(20.0), Some(3.0));
+        let err = apply_formula(&mut s, &ranges).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidResponse);
+
+        let mut s = status(Some(800.0), Some(20.0), Some(3.0));
+        apply_formula(&mut s, &ranges).unwrap();
+    }
+
+    #[test]
+    fn finalize_keeps_stats_within_configured_ranges() {
+        let ranges = StatRanges {
+            hp: StatBounds { min: 800, max: 900 },
+            ..StatRanges::default()
+        };
+        let mut s = status(Some(750.0), Some(60.0), Some(0.0));
+        finalize(&mut s, &ranges).unwrap();
+        assert!((800..=900).contains(&s.hp));
+        assert!(s.adjustments.is_empty());
+
+        let mut hp = 5000;
+        let (mut atk, mut def) = (50, 20);
+        let adjustments = check_stats(&mut hp, &mut atk, &mut def, &ranges).unwrap();
+        assert_eq!(hp, 900);
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].original, Some(5000));
+    }
+}
@@ -0,0 +1,31 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use rusqlite::Connection;
+use std::sync::{Mutex, MutexGuard};
+use tempfile::TempDir;
+
+use crate::assets::AssetStore;
+use crate::db;
+
+/// A throwaway install: an app data directory with its asset store and a
+/// migrated in-memory database.
+pub(crate) struct TestInstall {
+    pub dir: TempDir,
+    pub store: AssetStore,
+    /// Behind a mutex like the real connection in Tauri state.
+    pub db: Mutex<Connection>,
+}
+
+impl TestInstall {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AssetStore::open(dir.path()).unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::migrate(&mut conn).unwrap();
+        Self { dir, store, db: Mutex::new(conn) }
+    }
+
+    pub fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.db.lock().unwrap()
+    }
+}
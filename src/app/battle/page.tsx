@@ -8,6 +8,7 @@ import { RobotRecord } from '@/types/robot';
 import { Canvas, useFrame } from '@react-three/fiber';
 import { OrbitControls, Grid, Sparkles } from '@react-three/drei';
 import * as THREE from 'three';
+import { readAsset } from '@/lib/assets';
 
 function createBurstDirection(seed: number): THREE.Vector3 {
     const phi = (seed * 2.399963229728653) % (Math.PI * 2);
@@ -117,15 +118,14 @@ function BattleRobotModel({
         let disposed = false;
         async function loadAsset() {
             try {
-                const { readFile } = await import('@tauri-apps/plugin-fs');
-                const idleData = await readFile(robot.model_path);
+                const idleData = await readAsset(robot.model_path);
                 const idleBlob = new Blob([idleData], { type: 'model/gltf-binary' });
                 idleU = URL.createObjectURL(idleBlob);
                 if (disposed) return;
                 setIdleUrl(idleU);
 
                 if (robot.attack_model_path && robot.attack_model_path !== robot.model_path) {
-                    const attackData = await readFile(robot.attack_model_path);
+                    const attackData = await readAsset(robot.attack_model_path);
                     const attackBlob = new Blob([attackData], { type: 'model/gltf-binary' });
                     attackU = URL.createObjectURL(attackBlob);
                     if (disposed) return;
@@ -5,6 +5,33 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useStore } from '@/store/useStore';
 import RobotViewer from '@/components/RobotViewer';
+import { DuplicateMatch, PipelineEvent, PipelineStage } from '@/types/robot';
+import { assetMimeType, readAsset } from '@/lib/assets';
+
+const STAGE_LABELS: Record<PipelineStage, string> = {
+  queued: "Waiting for a free construction dock",
+  analyzing_food: "Analyzing food and generating stats",
+  generating_image: "Generating robot concept image",
+  generating_mesh: "Image to 3D Base Model",
+  rigging: "Rigging Model",
+  animating: "Applying Animation",
+  downloading: "Downloading models",
+  saving: "Saving robot",
+};
+
+function describePipelineEvent(event: PipelineEvent): string | null {
+  switch (event.type) {
+    case 'progress': {
+      const detail = event.detail ? ` (${event.detail})` : "";
+      const eta = event.eta_ms != null ? ` · ETA ${Math.ceil(event.eta_ms / 1000)}s` : "";
+      return `${STAGE_LABELS[event.stage]}${detail}: ${event.stage_percent}%${eta}`;
+    }
+    case 'rigging_fallback':
+      return "Rigging/animation unavailable. Falling back to static model...";
+    default:
+      return null;
+  }
+}
 
 function LocalImage({ path, alt, className }: { path: string, alt: string, className?: string }) {
   const [src, setSrc] = React.useState<string | null>(null);
@@ -15,9 +42,8 @@ function LocalImage({ path, alt, className }: { path: string, alt: string, class
 
     async function load() {
       try {
-        const { readFile } = await import('@tauri-apps/plugin-fs');
-        const data = await readFile(path);
-        const blob = new Blob([data], { type: 'image/png' });
+        const data = await readAsset(path);
+        const blob = new Blob([data], { type: assetMimeType(path) });
         url = URL.createObjectURL(blob);
         setSrc(url);
       } catch (err) {
@@ -48,28 +74,44 @@ export default function ConstructionPage() {
   // Pipeline State
   const [loading, setLoading] = useState(false);
   const [progressMsg, setProgressMsg] = useState("");
+  const [overallPercent, setOverallPercent] = useState(0);
   const [pipelineStats, setPipelineStats] = useState<any>(null);
   const [pipelineImages, setPipelineImages] = useState<any>(null);
   const [finishedRobot, setFinishedRobot] = useState<any>(null);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const [duplicate, setDuplicate] = useState<DuplicateMatch | null>(null);
+  const jobIdRef = useRef<string | null>(null);
 
   React.useEffect(() => {
     const unlisteners: (() => void)[] = [];
 
     const setupListeners = async () => {
-      const u1 = await listen<string>("pipeline-progress", (event) => {
-        setProgressMsg(event.payload);
+      // Several jobs can run at once, so ignore events from jobs this page didn't start.
+      const u1 = await listen<PipelineEvent>("pipeline-progress", (event) => {
+        if (event.payload.job_id !== jobIdRef.current) return;
+        const message = describePipelineEvent(event.payload);
+        if (message) setProgressMsg(message);
+        if ('overall_percent' in event.payload) setOverallPercent(event.payload.overall_percent);
       });
       unlisteners.push(u1);
 
-      const u2 = await listen<any>("pipeline-stats", (event) => {
-        setPipelineStats(event.payload);
+      const u2 = await listen<{ job_id: string; stats: any }>("pipeline-stats", (event) => {
+        if (event.payload.job_id !== jobIdRef.current) return;
+        setPipelineStats(event.payload.stats);
       });
       unlisteners.push(u2);
 
       const u3 = await listen<any>("pipeline-images", (event) => {
+        if (event.payload.job_id !== jobIdRef.current) return;
         setPipelineImages(event.payload);
       });
       unlisteners.push(u3);
+
+      const u4 = await listen<{ job_id: string }>("pipeline-started", (event) => {
+        jobIdRef.current = event.payload.job_id;
+        setJobId(event.payload.job_id);
+      });
+      unlisteners.push(u4);
     };
 
     setupListeners();
@@ -107,17 +149,20 @@ export default function ConstructionPage() {
 
   const handleFileSelected = (f: File) => {
     setFile(f);
+    setDuplicate(null);
     const objectUrl = URL.createObjectURL(f);
     setPreviewUrl(objectUrl);
   };
 
-  const startPipeline = async () => {
+  const startPipeline = async (allowDuplicate = false) => {
     if (!file) return;
 
     try {
+      setDuplicate(null);
       setLoading(true);
       setIsGenerating(true);
       setProgressMsg("Reading image data...");
+      setOverallPercent(0);
       setPipelineStats(null);
       setPipelineImages(null);
       setFinishedRobot(null);
@@ -130,10 +175,21 @@ export default function ConstructionPage() {
       const mimeType = file.type;
       const dataUri = `data:${mimeType};base64,${base64String}`;
 
+      if (!allowDuplicate) {
+        setProgressMsg("Checking for duplicate photos...");
+        const match = await invoke<DuplicateMatch | null>("check_duplicate", { base64Image: dataUri });
+        if (match) {
+          setDuplicate(match);
+          setProgressMsg("");
+          return;
+        }
+      }
+
       setProgressMsg("Sending data to Rust backend...");
 
       const newRobot = await invoke("run_generation_pipeline", {
         base64Image: dataUri,
+        allowDuplicate,
       });
 
       // Synchronize new robot with global Zustand state so it appears in Encyclopedia
@@ -143,6 +199,7 @@ export default function ConstructionPage() {
       ]);
 
       setFinishedRobot(newRobot);
+      setOverallPercent(100);
       setProgressMsg("Construction Complete!");
     } catch (err) {
       console.error(err);
@@ -156,6 +213,17 @@ export default function ConstructionPage() {
     } finally {
       setLoading(false);
       setIsGenerating(false);
+      jobIdRef.current = null;
+      setJobId(null);
+    }
+  };
+
+  const cancelPipeline = async () => {
+    if (!jobId) return;
+    try {
+      await invoke("cancel_generation", { jobId });
+    } catch (err) {
+      console.error("Failed to cancel generation:", err);
     }
   };
 
@@ -192,7 +260,7 @@ export default function ConstructionPage() {
             <input
               ref={fileInputRef}
               type="file"
-              accept="image/png, image/jpeg, image/heic"
+              accept="image/png, image/jpeg, image/webp"
               onChange={handleChange}
               className="hidden"
             />
@@ -208,6 +276,35 @@ export default function ConstructionPage() {
                 >
                   {loading ? "INITIALIZING..." : "INITIATE PIPELINE"}
                 </button>
+                {duplicate && !loading && (
+                  <div onClick={(e) => e.stopPropagation()} className="w-full rounded-xl border border-yellow-500/30 bg-yellow-500/10 p-3 text-sm flex flex-col gap-2">
+                    <p className="text-yellow-400 font-mono">
+                      This photo was already used to build <span className="font-bold">{duplicate.robot.name}</span>.
+                    </p>
+                    <div className="flex gap-2 justify-center">
+                      <button
+                        onClick={() => { setFinishedRobot(duplicate.robot); setDuplicate(null); }}
+                        className="px-4 py-2 rounded-lg font-bold border border-zinc-700 text-zinc-200 hover:border-zinc-500"
+                      >
+                        USE EXISTING
+                      </button>
+                      <button
+                        onClick={() => startPipeline(true)}
+                        className="px-4 py-2 rounded-lg font-bold border border-red-500/40 text-red-400 hover:border-red-400"
+                      >
+                        BUILD ANYWAY
+                      </button>
+                    </div>
+                  </div>
+                )}
+                {loading && jobId && (
+                  <button
+                    onClick={(e) => { e.stopPropagation(); cancelPipeline(); }}
+                    className="px-6 py-2 rounded-xl text-sm font-bold border border-zinc-700 text-zinc-400 hover:text-white hover:border-zinc-500 transition-all"
+                  >
+                    ABORT
+                  </button>
+                )}
               </div>
             ) : (
               <>
@@ -216,7 +313,7 @@ export default function ConstructionPage() {
                 </div>
                 <p className="text-zinc-300 font-medium text-lg">Select Food Target</p>
                 <p className="text-zinc-500 text-sm mt-2">Drag and drop an image here, or click to browse.</p>
-                <p className="text-zinc-600 text-xs mt-1">Accepts PNG, JPEG, HEIC</p>
+                <p className="text-zinc-600 text-xs mt-1">Accepts PNG, JPEG, WebP (HEIC: export as JPEG first)</p>
               </>
             )}
           </div>
@@ -271,6 +368,11 @@ export default function ConstructionPage() {
                 <div className="flex-1 truncate">
                   <div className="text-xs font-mono text-zinc-500">SYSTEM STATUS</div>
                   <div className="text-sm font-medium text-red-100 truncate">{progressMsg || "Standby"}</div>
+                  {loading && (
+                    <div className="mt-2 h-1 w-full rounded-full bg-zinc-800 overflow-hidden">
+                      <div className="h-full bg-red-500 transition-all duration-500" style={{ width: `${overallPercent}%` }} />
+                    </div>
+                  )}
                 </div>
               </div>
             )}
@@ -1,10 +1,12 @@
 "use client";
 import React, { useEffect, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { Search, Database, Clock, Crosshair, Shield, Heart } from 'lucide-react';
+import { open } from '@tauri-apps/plugin-dialog';
+import { Search, Database, Clock, Crosshair, Shield, Heart, Download, Upload } from 'lucide-react';
 import RobotViewer from '@/components/RobotViewer';
-import { RobotRecord } from '@/types/robot';
+import { AppError, ConflictPolicy, ImportResult, RobotRecord, RobotSearchHit } from '@/types/robot';
 import { useStore } from '@/store/useStore';
+import { assetMimeType, readAsset } from '@/lib/assets';
 
 // Helper component to securely load local images via Tauri filesystem API 
 function LocalImage({ path, alt, className }: { path: string, alt: string, className?: string }) {
@@ -16,9 +18,8 @@ function LocalImage({ path, alt, className }: { path: string, alt: string, class
 
         async function load() {
             try {
-                const { readFile } = await import('@tauri-apps/plugin-fs');
-                const data = await readFile(path);
-                const blob = new Blob([data], { type: 'image/png' });
+                const data = await readAsset(path);
+                const blob = new Blob([data], { type: assetMimeType(path) });
                 url = URL.createObjectURL(blob);
                 setSrc(url);
             } catch (err) {
@@ -36,12 +37,72 @@ function LocalImage({ path, alt, className }: { path: string, alt: string, class
     return <img src={src} alt={alt} className={className} />;
 }
 
+// Renders a search snippet. Only the <mark> markers become elements; the rest
+// is robot text and is rendered as plain (escaped) text.
+function Highlighted({ snippet }: { snippet: string }) {
+    const parts = snippet.split(/<mark>|<\/mark>/);
+    return (
+        <>
+            {parts.map((part, i) =>
+                i % 2 === 1 ? <mark key={i} className="bg-blue-500/30 text-blue-100 rounded-sm">{part}</mark> : part
+            )}
+        </>
+    );
+}
+
+const SEARCH_DEBOUNCE_MS = 200;
+
 
 export default function EncyclopediaPage() {
     const [robots, setRobots] = useState<RobotRecord[]>([]);
     const [loading, setLoading] = useState(true);
     const [searchQuery, setSearchQuery] = useState("");
+    // Ranked results from `search_robots`, or null while the search box is empty.
+    const [searchHits, setSearchHits] = useState<RobotSearchHit[] | null>(null);
     const [selectedRobot, setSelectedRobot] = useState<RobotRecord | null>(null);
+    const [exportMessage, setExportMessage] = useState<string | null>(null);
+
+    const [importMessage, setImportMessage] = useState<string | null>(null);
+    // Package waiting for the user to choose how to resolve an id conflict.
+    const [pendingImport, setPendingImport] = useState<{ path: string, existing: RobotRecord } | null>(null);
+
+    const importPackage = async (path: string, onConflict?: ConflictPolicy) => {
+        setPendingImport(null);
+        setImportMessage("Importing...");
+        try {
+            const result = await invoke<ImportResult>('import_robot', { path, onConflict });
+            if (result.status === 'conflict') {
+                setPendingImport({ path, existing: result.robot });
+                setImportMessage(null);
+                return;
+            }
+            if (result.status === 'imported') {
+                const next = [result.robot, ...robots.filter((r) => r.id !== result.robot.id)];
+                setRobots(next);
+                useStore.getState().setRobots(next);
+            }
+            setSelectedRobot(result.robot);
+            setImportMessage(result.status === 'imported' ? `Imported ${result.robot.name}` : `Skipped ${result.robot.name}`);
+        } catch (err) {
+            setImportMessage(`Import failed: ${(err as AppError)?.message ?? String(err)}`);
+        }
+    };
+
+    // The backend reads the file itself, so large packages never cross IPC.
+    const pickImportFile = async () => {
+        const path = await open({ multiple: false, directory: false, filters: [{ name: 'Robot package', extensions: ['ffr'] }] });
+        if (path) importPackage(path);
+    };
+
+    const exportRobot = async (robot: RobotRecord) => {
+        setExportMessage("Exporting...");
+        try {
+            const path = await invoke<string>('export_robot', { id: robot.id });
+            setExportMessage(`Saved to ${path}`);
+        } catch (err) {
+            setExportMessage(`Export failed: ${(err as AppError)?.message ?? String(err)}`);
+        }
+    };
 
     useEffect(() => {
         async function loadRobots() {
@@ -63,10 +124,30 @@ export default function EncyclopediaPage() {
         loadRobots();
     }, []);
 
-    const filteredRobots = robots.filter(r =>
-        r.name.toLowerCase().includes(searchQuery.toLowerCase()) ||
-        r.lore.toLowerCase().includes(searchQuery.toLowerCase())
-    );
+    useEffect(() => {
+        const query = searchQuery.trim();
+        if (!query) {
+            setSearchHits(null);
+            return;
+        }
+        let stale = false;
+        const timer = setTimeout(async () => {
+            try {
+                const hits = await invoke<RobotSearchHit[]>('search_robots', { query });
+                if (!stale) setSearchHits(hits);
+            } catch (e) {
+                console.error("Search failed:", e);
+            }
+        }, SEARCH_DEBOUNCE_MS);
+        return () => {
+            stale = true;
+            clearTimeout(timer);
+        };
+    }, [searchQuery, robots]);
+
+    const listedRobots: { robot: RobotRecord, hit?: RobotSearchHit }[] = searchHits
+        ? searchHits.map((hit) => ({ robot: hit.robot, hit }))
+        : robots.map((robot) => ({ robot }));
 
     return (
         <div className="w-full h-full flex flex-col gap-6 max-w-7xl mx-auto">
@@ -76,18 +157,38 @@ export default function EncyclopediaPage() {
                     <p className="text-zinc-500 font-mono text-sm uppercase tracking-widest mt-1">Robot Database & Specifications</p>
                 </div>
 
-                <div className="relative w-full md:w-64">
-                    <Search size={18} className="absolute left-3 top-1/2 -translate-y-1/2 text-zinc-500" />
-                    <input
-                        type="text"
-                        placeholder="Search database..."
-                        className="w-full bg-zinc-900 border border-zinc-700 rounded-full py-2 pl-10 pr-4 text-sm focus:outline-none focus:border-blue-500 transition-colors"
-                        value={searchQuery}
-                        onChange={(e) => setSearchQuery(e.target.value)}
-                    />
+                <div className="flex items-center gap-3">
+                    <button
+                        onClick={pickImportFile}
+                        className="flex items-center gap-2 px-4 py-2 rounded-full border border-zinc-700 text-sm text-zinc-300 hover:border-blue-500 hover:text-blue-400 transition-colors shrink-0"
+                    >
+                        <Upload size={16} /> IMPORT
+                    </button>
+                    <div className="relative w-full md:w-64">
+                        <Search size={18} className="absolute left-3 top-1/2 -translate-y-1/2 text-zinc-500" />
+                        <input
+                            type="text"
+                            placeholder="Search database..."
+                            className="w-full bg-zinc-900 border border-zinc-700 rounded-full py-2 pl-10 pr-4 text-sm focus:outline-none focus:border-blue-500 transition-colors"
+                            value={searchQuery}
+                            onChange={(e) => setSearchQuery(e.target.value)}
+                        />
+                    </div>
                 </div>
             </header>
 
+            {pendingImport && (
+                <div className="rounded-xl border border-yellow-500/30 bg-yellow-500/10 p-3 text-sm flex flex-wrap items-center gap-3">
+                    <span className="text-yellow-400 font-mono flex-1">
+                        {pendingImport.existing.name} is already in the database.
+                    </span>
+                    <button onClick={() => importPackage(pendingImport.path, 'skip')} className="px-3 py-1.5 rounded-lg border border-zinc-700 text-zinc-300 hover:border-zinc-500">SKIP</button>
+                    <button onClick={() => importPackage(pendingImport.path, 'overwrite')} className="px-3 py-1.5 rounded-lg border border-red-500/40 text-red-400 hover:border-red-400">OVERWRITE</button>
+                    <button onClick={() => importPackage(pendingImport.path, 'duplicate')} className="px-3 py-1.5 rounded-lg border border-blue-500/40 text-blue-400 hover:border-blue-400">IMPORT AS COPY</button>
+                </div>
+            )}
+            {importMessage && <p className="text-xs font-mono text-zinc-500 -mt-4">{importMessage}</p>}
+
             <div className="flex-1 grid grid-cols-1 lg:grid-cols-3 gap-6 h-full overflow-hidden">
 
                 {/* Left Col: Grid List */}
@@ -95,19 +196,19 @@ export default function EncyclopediaPage() {
 
                     <h2 className="text-lg font-bold flex items-center gap-2 border-b border-zinc-800 pb-3">
                         <Database size={18} className="text-blue-500" />
-                        Archived Units ({filteredRobots.length})
+                        Archived Units ({listedRobots.length})
                     </h2>
 
                     <div className="flex-1 overflow-y-auto pr-2 custom-scrollbar flex flex-col gap-3">
                         {loading ? (
                             <div className="text-center text-zinc-500 py-10 animate-pulse">Accessing Database...</div>
-                        ) : filteredRobots.length === 0 ? (
+                        ) : listedRobots.length === 0 ? (
                             <div className="text-center text-zinc-500 py-10">No matching records found.</div>
                         ) : (
-                            filteredRobots.map(robot => (
+                            listedRobots.map(({ robot, hit }) => (
                                 <button
                                     key={robot.id}
-                                    onClick={() => setSelectedRobot(robot)}
+                                    onClick={() => { setSelectedRobot(robot); setExportMessage(null); }}
                                     className={`flex items-center gap-4 p-3 rounded-xl border text-left transition-all ${selectedRobot?.id === robot.id
                                         ? "bg-blue-900/20 border-blue-500/50 shadow-[0_0_15px_-3px_rgba(59,130,246,0.2)]"
                                         : "bg-zinc-950 border-zinc-800 hover:border-zinc-600 hover:bg-zinc-800/50"
@@ -115,12 +216,19 @@ export default function EncyclopediaPage() {
                                 >
                                     <LocalImage path={robot.image_path} className="w-16 h-16 rounded-lg object-cover border border-zinc-800 shrink-0" alt={robot.name} />
                                     <div className="flex-1 min-w-0">
-                                        <h3 className="font-bold text-zinc-100 truncate">{robot.name}</h3>
+                                        <h3 className="font-bold text-zinc-100 truncate">
+                                            {hit ? <Highlighted snippet={hit.name_snippet} /> : robot.name}
+                                        </h3>
                                         <div className="flex gap-3 mt-1 text-xs font-mono text-zinc-500">
                                             <span className="flex items-center gap-1"><Heart size={10} /> {robot.hp}</span>
                                             <span className="flex items-center gap-1"><Crosshair size={10} /> {robot.atk}</span>
                                             <span className="flex items-center gap-1"><Shield size={10} /> {robot.def}</span>
                                         </div>
+                                        {hit && hit.lore_snippet.includes('<mark>') && (
+                                            <p className="mt-1 text-xs text-zinc-400 line-clamp-2">
+                                                <Highlighted snippet={hit.lore_snippet} />
+                                            </p>
+                                        )}
                                     </div>
                                 </button>
                             ))
@@ -150,7 +258,24 @@ export default function EncyclopediaPage() {
                                         <Clock size={12} />
                                         {new Date(selectedRobot.created_at * 1000).toLocaleDateString()}
                                     </span>
+                                    {selectedRobot.stats_adjusted && (
+                                        <span
+                                            className="flex items-center gap-1 bg-amber-950/30 text-amber-400 px-2 py-1 rounded border border-amber-900/50"
+                                            title={selectedRobot.stat_adjustments
+                                                .map((a) => a.kind === 'clamped' ? `${a.field}: ${a.original} → clamped` : `${a.field}: defaulted`)
+                                                .join('\n')}
+                                        >
+                                            STATS ADJUSTED
+                                        </span>
+                                    )}
+                                    <button
+                                        onClick={() => exportRobot(selectedRobot)}
+                                        className="flex items-center gap-1 bg-zinc-950 px-2 py-1 rounded border border-zinc-800 hover:border-blue-500/50 hover:text-blue-400 transition-colors"
+                                    >
+                                        <Download size={12} /> EXPORT .FFR
+                                    </button>
                                 </div>
+                                {exportMessage && <p className="mt-2 text-xs font-mono text-zinc-500 break-all">{exportMessage}</p>}
                             </div>
 
                             {/* 3D Viewer Area */}
@@ -174,6 +299,16 @@ export default function EncyclopediaPage() {
                                 </div>
                             </div>
 
+                            {/* Nutrition analysis behind the stats */}
+                            {selectedRobot.nutrition?.food_name && (
+                                <div className="mb-6 shrink-0 bg-zinc-950 px-5 py-3 rounded-2xl border border-zinc-800 flex flex-wrap gap-x-6 gap-y-1 text-xs font-mono text-zinc-400">
+                                    <span className="text-zinc-300 font-bold">{selectedRobot.nutrition.food_name}</span>
+                                    {selectedRobot.nutrition.calories != null && <span>{Math.round(selectedRobot.nutrition.calories)} kcal</span>}
+                                    {selectedRobot.nutrition.protein != null && <span>PROTEIN {selectedRobot.nutrition.protein.toFixed(1)} g</span>}
+                                    {selectedRobot.nutrition.fiber != null && <span>FIBER {selectedRobot.nutrition.fiber.toFixed(1)} g</span>}
+                                </div>
+                            )}
+
                             {/* Lore & Materials */}
                             <div className="flex flex-col md:flex-row gap-6 shrink-0">
 
@@ -0,0 +1,349 @@
+"use client";
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { KeyRound, Wifi, Trash2, Save, SlidersHorizontal, HardDrive } from 'lucide-react';
+import { ApiKeyStatus, AppError, ConnectionCheck, KeyedProvider, MaintenanceReport, OrphanAction, Settings } from '@/types/robot';
+
+const PROVIDER_LABELS: Record<KeyedProvider, string> = {
+    gemini: "Gemini",
+    meshy: "Meshy AI",
+};
+
+const SOURCE_LABELS: Record<ApiKeyStatus['source'], string> = {
+    env: "ENV OVERRIDE",
+    stored: "STORED",
+    missing: "NOT SET",
+};
+
+function errorMessage(err: unknown): string {
+    return (err as AppError)?.message ?? String(err);
+}
+
+function ApiKeyRow({ status, onChange }: { status: ApiKeyStatus, onChange: (s: ApiKeyStatus) => void }) {
+    const [draft, setDraft] = useState("");
+    const [busy, setBusy] = useState(false);
+    const [message, setMessage] = useState<{ ok: boolean, text: string } | null>(null);
+
+    const save = async () => {
+        setBusy(true);
+        setMessage(null);
+        try {
+            await invoke('validate_api_key', { provider: status.provider, apiKey: draft });
+            onChange(await invoke<ApiKeyStatus>('set_api_key', { provider: status.provider, apiKey: draft }));
+            setDraft("");
+            setMessage({ ok: true, text: "Key verified and saved." });
+        } catch (err) {
+            setMessage({ ok: false, text: errorMessage(err) });
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    const clear = async () => {
+        setBusy(true);
+        setMessage(null);
+        try {
+            onChange(await invoke<ApiKeyStatus>('clear_api_key', { provider: status.provider }));
+        } catch (err) {
+            setMessage({ ok: false, text: errorMessage(err) });
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    return (
+        <div className="rounded-lg border border-zinc-800 bg-zinc-900/50 p-4 flex flex-col gap-3">
+            <div className="flex items-center justify-between">
+                <span className="font-bold tracking-wide">{PROVIDER_LABELS[status.provider]}</span>
+                <span className="font-mono text-xs text-zinc-500">
+                    {SOURCE_LABELS[status.source]}{status.hint && ` ••••${status.hint}`}
+                </span>
+            </div>
+            <div className="flex gap-2">
+                <input
+                    type="password"
+                    value={draft}
+                    onChange={(e) => setDraft(e.target.value)}
+                    placeholder="Paste API key"
+                    className="flex-1 bg-zinc-950 border border-zinc-800 rounded-lg px-3 py-2 font-mono text-sm focus:outline-none focus:border-red-500/50"
+                />
+                <button
+                    onClick={save}
+                    disabled={busy || !draft.trim()}
+                    className="flex items-center gap-2 px-3 py-2 rounded-lg bg-red-500/10 text-red-500 border border-red-500/30 disabled:opacity-40"
+                >
+                    <Save size={16} /> SAVE
+                </button>
+                <button
+                    onClick={clear}
+                    disabled={busy || status.source !== 'stored'}
+                    className="flex items-center gap-2 px-3 py-2 rounded-lg text-zinc-400 border border-zinc-800 hover:text-zinc-100 disabled:opacity-40"
+                >
+                    <Trash2 size={16} />
+                </button>
+            </div>
+            {status.source === 'env' && (
+                <p className="text-xs text-zinc-500 font-mono">The environment variable takes precedence over a stored key.</p>
+            )}
+            {message && (
+                <p className={`text-xs font-mono ${message.ok ? "text-green-400" : "text-red-400"}`}>{message.text}</p>
+            )}
+        </div>
+    );
+}
+
+function Field({ label, children }: { label: string, children: React.ReactNode }) {
+    return (
+        <label className="flex items-center justify-between gap-4 text-sm">
+            <span className="text-zinc-400 font-mono">{label}</span>
+            {children}
+        </label>
+    );
+}
+
+const inputClass = "w-48 bg-zinc-950 border border-zinc-800 rounded-lg px-3 py-1.5 font-mono text-sm focus:outline-none focus:border-red-500/50";
+
+function GenerationSettings() {
+    const [draft, setDraft] = useState<Settings | null>(null);
+    const [message, setMessage] = useState<{ ok: boolean, text: string } | null>(null);
+
+    useEffect(() => {
+        invoke<Settings>('get_settings')
+            .then(setDraft)
+            .catch((err) => console.error("Failed to load settings:", err));
+    }, []);
+
+    if (!draft) return null;
+
+    // Edit a deep copy so React sees a new object.
+    const edit = (mutate: (s: Settings) => void) => {
+        const next: Settings = JSON.parse(JSON.stringify(draft));
+        mutate(next);
+        setDraft(next);
+    };
+    const num = (e: React.ChangeEvent<HTMLInputElement>) => Number(e.target.value);
+
+    const save = async () => {
+        setMessage(null);
+        try {
+            setDraft(await invoke<Settings>('update_settings', { settings: draft }));
+            setMessage({ ok: true, text: "Settings saved." });
+        } catch (err) {
+            setMessage({ ok: false, text: errorMessage(err) });
+        }
+    };
+
+    return (
+        <section className="flex flex-col gap-3">
+            <h2 className="flex items-center gap-2 text-sm font-bold tracking-widest text-zinc-400"><SlidersHorizontal size={16} /> GENERATION</h2>
+            <div className="rounded-lg border border-zinc-800 bg-zinc-900/50 p-4 flex flex-col gap-2">
+                <Field label="Stats model">
+                    <input className={inputClass} value={draft.gemini.stats_model} onChange={(e) => edit((s) => { s.gemini.stats_model = e.target.value; })} />
+                </Field>
+                <Field label="Image model">
+                    <input className={inputClass} value={draft.gemini.image_model} onChange={(e) => edit((s) => { s.gemini.image_model = e.target.value; })} />
+                </Field>
+                <Field label="PBR textures">
+                    <input type="checkbox" checked={draft.meshy.enable_pbr} onChange={(e) => edit((s) => { s.meshy.enable_pbr = e.target.checked; })} />
+                </Field>
+                <Field label="Idle action id">
+                    <input type="number" className={inputClass} value={draft.meshy.idle_action_id} onChange={(e) => edit((s) => { s.meshy.idle_action_id = num(e); })} />
+                </Field>
+                <Field label="Attack action id">
+                    <input type="number" className={inputClass} value={draft.meshy.attack_action_id} onChange={(e) => edit((s) => { s.meshy.attack_action_id = num(e); })} />
+                </Field>
+                <Field label="Initial poll delay (ms)">
+                    <input type="number" className={inputClass} value={draft.meshy.polling.initial_delay_ms} onChange={(e) => edit((s) => { s.meshy.polling.initial_delay_ms = num(e); })} />
+                </Field>
+                <Field label="Max poll delay (ms)">
+                    <input type="number" className={inputClass} value={draft.meshy.polling.max_delay_ms} onChange={(e) => edit((s) => { s.meshy.polling.max_delay_ms = num(e); })} />
+                </Field>
+                <Field label="Backoff multiplier">
+                    <input type="number" step="0.1" className={inputClass} value={draft.meshy.polling.backoff_multiplier} onChange={(e) => edit((s) => { s.meshy.polling.backoff_multiplier = num(e); })} />
+                </Field>
+                <Field label="Mesh timeout (s)">
+                    <input type="number" className={inputClass} value={draft.meshy.polling.mesh_timeout_secs} onChange={(e) => edit((s) => { s.meshy.polling.mesh_timeout_secs = num(e); })} />
+                </Field>
+                <Field label="Rigging timeout (s)">
+                    <input type="number" className={inputClass} value={draft.meshy.polling.rigging_timeout_secs} onChange={(e) => edit((s) => { s.meshy.polling.rigging_timeout_secs = num(e); })} />
+                </Field>
+                <Field label="Animation timeout (s)">
+                    <input type="number" className={inputClass} value={draft.meshy.polling.animation_timeout_secs} onChange={(e) => edit((s) => { s.meshy.polling.animation_timeout_secs = num(e); })} />
+                </Field>
+                {(['hp', 'atk', 'def'] as const).map((stat) => (
+                    <Field key={stat} label={`${stat.toUpperCase()} range`}>
+                        <span className="flex gap-2">
+                            <input type="number" className="w-22 bg-zinc-950 border border-zinc-800 rounded-lg px-2 py-1.5 font-mono text-sm" value={draft.stats[stat].min} onChange={(e) => edit((s) => { s.stats[stat].min = num(e); })} />
+                            <input type="number" className="w-22 bg-zinc-950 border border-zinc-800 rounded-lg px-2 py-1.5 font-mono text-sm" value={draft.stats[stat].max} onChange={(e) => edit((s) => { s.stats[stat].max = num(e); })} />
+                        </span>
+                    </Field>
+                ))}
+                {([['calories', 'HP from kcal'], ['protein', 'ATK from protein (g)'], ['fiber', 'DEF from fiber (g)']] as const).map(([nutrient, label]) => (
+                    <Field key={nutrient} label={label}>
+                        <span className="flex gap-2">
+                            <input type="number" className="w-22 bg-zinc-950 border border-zinc-800 rounded-lg px-2 py-1.5 font-mono text-sm" value={draft.stats.formula[nutrient].min} onChange={(e) => edit((s) => { s.stats.formula[nutrient].min = num(e); })} />
+                            <input type="number" className="w-22 bg-zinc-950 border border-zinc-800 rounded-lg px-2 py-1.5 font-mono text-sm" value={draft.stats.formula[nutrient].max} onChange={(e) => edit((s) => { s.stats.formula[nutrient].max = num(e); })} />
+                        </span>
+                    </Field>
+                ))}
+                <Field label="Out-of-range nutrition">
+                    <select className={inputClass} value={draft.stats.out_of_range} onChange={(e) => edit((s) => { s.stats.out_of_range = e.target.value as Settings['stats']['out_of_range']; })}>
+                        <option value="clamp">Clamp into range</option>
+                        <option value="reject">Reject result</option>
+                    </select>
+                </Field>
+                <button
+                    onClick={save}
+                    className="self-end mt-2 flex items-center gap-2 px-3 py-2 rounded-lg bg-red-500/10 text-red-500 border border-red-500/30"
+                >
+                    <Save size={16} /> SAVE
+                </button>
+                {message && (
+                    <p className={`text-xs font-mono ${message.ok ? "text-green-400" : "text-red-400"}`}>{message.text}</p>
+                )}
+            </div>
+        </section>
+    );
+}
+
+function formatBytes(bytes: number): string {
+    if (bytes < 1024) return `${bytes} B`;
+    if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+    return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+}
+
+function StorageMaintenance() {
+    const [report, setReport] = useState<MaintenanceReport | null>(null);
+    const [busy, setBusy] = useState(false);
+    const [error, setError] = useState<string | null>(null);
+
+    const run = async (action: OrphanAction) => {
+        setBusy(true);
+        setError(null);
+        try {
+            setReport(await invoke<MaintenanceReport>('run_maintenance', { action }));
+        } catch (err) {
+            setError(errorMessage(err));
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    const hasOrphans = report?.action === 'report' && report.orphans.length > 0;
+
+    return (
+        <section className="flex flex-col gap-3">
+            <h2 className="flex items-center gap-2 text-sm font-bold tracking-widest text-zinc-400"><HardDrive size={16} /> STORAGE</h2>
+            <div className="rounded-lg border border-zinc-800 bg-zinc-900/50 p-4 flex flex-col gap-3">
+                <div className="flex gap-2">
+                    <button
+                        onClick={() => run('report')}
+                        disabled={busy}
+                        className="flex items-center gap-2 px-3 py-2 rounded-lg border border-zinc-800 text-zinc-300 hover:text-zinc-100 disabled:opacity-40"
+                    >
+                        {busy ? "CHECKING..." : "CHECK FILES"}
+                    </button>
+                    <button
+                        onClick={() => run('quarantine')}
+                        disabled={busy || !hasOrphans}
+                        className="px-3 py-2 rounded-lg border border-zinc-800 text-zinc-300 hover:text-zinc-100 disabled:opacity-40"
+                    >
+                        QUARANTINE ORPHANS
+                    </button>
+                    <button
+                        onClick={() => run('delete')}
+                        disabled={busy || !hasOrphans}
+                        className="flex items-center gap-2 px-3 py-2 rounded-lg bg-red-500/10 text-red-500 border border-red-500/30 disabled:opacity-40"
+                    >
+                        <Trash2 size={16} /> DELETE ORPHANS
+                    </button>
+                </div>
+                {report && (
+                    <div className="font-mono text-sm flex flex-col gap-1">
+                        <span className="text-zinc-400">
+                            Total {formatBytes(report.usage.total_bytes)} · robots {formatBytes(report.usage.asset_bytes)} · database {formatBytes(report.usage.database_bytes)} · quarantine {formatBytes(report.usage.quarantine_bytes)}
+                        </span>
+                        <span className={report.orphans.length ? "text-yellow-400" : "text-green-400"}>
+                            {report.orphans.length} orphaned files ({formatBytes(report.usage.orphan_bytes)})
+                            {report.action === 'quarantine' && report.orphans.length > 0 && " moved to quarantine"}
+                            {report.action === 'delete' && report.orphans.length > 0 && " deleted"}
+                        </span>
+                        <span className={report.missing.length ? "text-red-400" : "text-green-400"}>
+                            {report.missing.length} missing robot files
+                        </span>
+                        {report.missing.map((m) => (
+                            <span key={`${m.robot_id}-${m.field}`} className="text-xs text-zinc-500">
+                                {m.robot_name}: {m.field} ({m.asset_id})
+                            </span>
+                        ))}
+                    </div>
+                )}
+                {error && <p className="text-xs font-mono text-red-400">{error}</p>}
+            </div>
+        </section>
+    );
+}
+
+export default function SettingsPage() {
+    const [keys, setKeys] = useState<ApiKeyStatus[]>([]);
+    const [checks, setChecks] = useState<ConnectionCheck[] | null>(null);
+    const [testing, setTesting] = useState(false);
+
+    useEffect(() => {
+        invoke<ApiKeyStatus[]>('get_api_key_status')
+            .then(setKeys)
+            .catch((err) => console.error("Failed to load API key status:", err));
+    }, []);
+
+    const testConnections = async () => {
+        setTesting(true);
+        try {
+            setChecks(await invoke<ConnectionCheck[]>('test_connections'));
+        } catch (err) {
+            console.error("Connection test failed:", err);
+        } finally {
+            setTesting(false);
+        }
+    };
+
+    return (
+        <div className="w-full max-w-3xl mx-auto flex flex-col gap-6">
+            <header>
+                <h1 className="text-4xl font-black italic tracking-tighter text-zinc-100">SETTINGS</h1>
+                <p className="text-zinc-500 font-mono text-sm uppercase tracking-widest mt-1">API Keys, Connectivity, Generation & Storage</p>
+            </header>
+
+            <section className="flex flex-col gap-3">
+                <h2 className="flex items-center gap-2 text-sm font-bold tracking-widest text-zinc-400"><KeyRound size={16} /> API KEYS</h2>
+                {keys.map((status) => (
+                    <ApiKeyRow
+                        key={status.provider}
+                        status={status}
+                        onChange={(next) => setKeys((prev) => prev.map((k) => k.provider === next.provider ? next : k))}
+                    />
+                ))}
+            </section>
+
+            <section className="flex flex-col gap-3">
+                <button
+                    onClick={testConnections}
+                    disabled={testing}
+                    className="self-start flex items-center gap-2 px-4 py-2 rounded-lg border border-zinc-800 text-zinc-300 hover:text-zinc-100 disabled:opacity-40"
+                >
+                    <Wifi size={16} /> {testing ? "TESTING..." : "TEST CONNECTION"}
+                </button>
+                {checks?.map((check) => (
+                    <div key={check.provider} className="font-mono text-sm">
+                        <span className={check.ok ? "text-green-400" : "text-red-400"}>{check.ok ? "OK" : "FAIL"}</span>
+                        {" "}{PROVIDER_LABELS[check.provider]} ({check.latency_ms} ms)
+                        {check.error && <span className="text-zinc-500"> — {check.error.message}</span>}
+                    </div>
+                ))}
+            </section>
+
+            <GenerationSettings />
+
+            <StorageMaintenance />
+        </div>
+    );
+}
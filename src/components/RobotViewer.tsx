@@ -4,6 +4,7 @@ import { Canvas, useFrame } from '@react-three/fiber';
 import { OrbitControls, useGLTF, Environment } from '@react-three/drei';
 import * as THREE from 'three';
 import { clone as cloneSkeleton } from 'three/examples/jsm/utils/SkeletonUtils.js';
+import { readAsset } from '@/lib/assets';
 
 type CachedAssetUrl = {
     url: string;
@@ -16,8 +17,7 @@ const assetUrlCache = new Map<string, CachedAssetUrl>();
 const ASSET_URL_REVOKE_DELAY_MS = 30_000;
 
 async function createAssetUrlFromPath(path: string): Promise<string> {
-    const { readFile } = await import('@tauri-apps/plugin-fs');
-    const data = await readFile(path);
+    const data = await readAsset(path);
     const blob = new Blob([data], { type: 'model/gltf-binary' });
     return URL.createObjectURL(blob);
 }
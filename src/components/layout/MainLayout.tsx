@@ -2,18 +2,28 @@
 import React from 'react';
 import Link from 'next/link';
 import { usePathname } from 'next/navigation';
-import { Home, Hammer, Book, Swords } from 'lucide-react';
+import { Home, Hammer, Book, Swords, Settings } from 'lucide-react';
+import { invoke } from '@tauri-apps/api/core';
 import { useStore } from '@/store/useStore';
+import { DbStatus } from '@/types/robot';
 
 export default function MainLayout({ children }: { children: React.ReactNode }) {
     const pathname = usePathname();
     const isGenerating = useStore((state) => state.isGenerating);
+    const [dbStatus, setDbStatus] = React.useState<DbStatus | null>(null);
+
+    React.useEffect(() => {
+        invoke<DbStatus>('get_db_status')
+            .then(setDbStatus)
+            .catch((err) => console.error("Failed to fetch DB status:", err));
+    }, []);
 
     const navItems = [
         { label: "Home", href: "/", icon: Home },
         { label: "Construction", href: "/construction", icon: Hammer },
         { label: "Encyclopedia", href: "/encyclopedia", icon: Book },
         { label: "Battle Arena", href: "/battle", icon: Swords },
+        { label: "Settings", href: "/settings", icon: Settings },
     ];
 
     return (
@@ -59,6 +69,12 @@ export default function MainLayout({ children }: { children: React.ReactNode })
             <main className="flex-1 relative overflow-y-auto">
                 <div className="absolute inset-0 bg-[linear-gradient(rgba(255,255,255,0.02)_1px,transparent_1px),linear-gradient(90deg,rgba(255,255,255,0.02)_1px,transparent_1px)] bg-[size:40px_40px] pointer-events-none z-0" />
                 <div className="relative z-10 w-full h-full p-4 md:p-8">
+                    {dbStatus?.migration_error && (
+                        <div className="mb-4 rounded-lg border border-red-500/40 bg-red-500/10 px-4 py-3 text-sm text-red-300 font-mono">
+                            Database upgrade to v{dbStatus.migration_error.failed_version} failed
+                            (current v{dbStatus.schema_version}, expected v{dbStatus.target_version}): {dbStatus.migration_error.message}
+                        </div>
+                    )}
                     {children}
                 </div>
             </main>
@@ -0,0 +1,22 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/** Read a robot/job asset by the asset ID stored in the database. */
+export async function readAsset(assetId: string): Promise<Uint8Array> {
+    const path = await invoke<string>('resolve_asset', { assetId });
+    const { readFile } = await import('@tauri-apps/plugin-fs');
+    return readFile(path);
+}
+
+const MIME_TYPES: Record<string, string> = {
+    png: 'image/png',
+    jpg: 'image/jpeg',
+    jpeg: 'image/jpeg',
+    webp: 'image/webp',
+    glb: 'model/gltf-binary',
+};
+
+/** MIME type of an asset, from the extension the store gave its ID. */
+export function assetMimeType(assetId: string): string {
+    const extension = assetId.split('.').pop()?.toLowerCase() ?? '';
+    return MIME_TYPES[extension] ?? 'application/octet-stream';
+}
@@ -11,4 +11,246 @@ export interface RobotRecord {
     attack_model_path: string;
     created_at: number;
     generation_time_ms: number;
+    is_favorite: boolean;
+    stats_adjusted: boolean;
+    stat_adjustments: StatAdjustment[];
+    nutrition: Nutrition;
+    /** Version of the nutrition formula that computed the stats; null if the model chose them. */
+    stat_formula_version: number | null;
+    /** 64-bit perceptual hash of the source photo, as 16 hex digits. */
+    perceptual_hash: string | null;
+}
+
+/** Existing robot built from (almost) the same photo as an upload. */
+export interface DuplicateMatch {
+    robot: RobotRecord;
+    /** Differing hash bits out of 64; 0 means visually identical. */
+    distance: number;
+}
+
+export interface Nutrition {
+    food_name: string | null;
+    calories: number | null;
+    protein: number | null;
+    fiber: number | null;
+}
+
+export interface StatAdjustment {
+    field: string;
+    kind: 'defaulted' | 'clamped';
+    original: number | null;
+}
+
+export interface RobotUpdate {
+    name?: string;
+    lore?: string;
+    is_favorite?: boolean;
+}
+
+export type RobotSortKey = 'created_at' | 'hp' | 'atk' | 'def' | 'generation_time_ms';
+
+export interface StatRange {
+    min?: number;
+    max?: number;
+}
+
+export interface RobotQuery {
+    sort_by?: RobotSortKey;
+    direction?: 'asc' | 'desc';
+    limit?: number;
+    offset?: number;
+    hp?: StatRange;
+    atk?: StatRange;
+    def?: StatRange;
+    favorites_only?: boolean;
+}
+
+export interface RobotPage {
+    robots: RobotRecord[];
+    total: number;
+    next_offset: number | null;
+}
+
+export interface RobotSearchHit {
+    robot: RobotRecord;
+    /** Raw text with matches wrapped in <mark>…</mark>; escape everything else before rendering. */
+    name_snippet: string;
+    lore_snippet: string;
+    rank: number;
+}
+
+export type JobStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface GenerationJob {
+    id: string;
+    status: JobStatus;
+    original_image_path: string;
+    /** SHA-256 of the preprocessed upload. */
+    image_hash: string | null;
+    perceptual_hash: string | null;
+    stats_json: string | null;
+    generated_image_path: string | null;
+    image_task_id: string | null;
+    rig_task_id: string | null;
+    idle_anim_task_id: string | null;
+    attack_anim_task_id: string | null;
+    rigging_error: string | null;
+    robot_id: string | null;
+    error: string | null;
+    created_at_ms: number;
+    updated_at_ms: number;
+}
+
+export type ErrorCode =
+    | 'missing_api_key'
+    | 'unauthorized'
+    | 'quota_exceeded'
+    | 'rate_limited'
+    | 'timeout'
+    | 'network'
+    | 'content_refused'
+    | 'invalid_response'
+    | 'provider_unavailable'
+    | 'provider_error'
+    | 'task_failed'
+    | 'cancelled'
+    | 'not_found'
+    | 'invalid_input'
+    | 'database'
+    | 'io'
+    | 'internal'
+    | 'duplicate';
+
+/** Error payload rejected by every Tauri command. */
+export interface AppError {
+    code: ErrorCode;
+    message: string;
+    retryable: boolean;
+    provider: 'gemini' | 'meshy' | 'database' | 'filesystem' | 'mock' | null;
+}
+
+export type PipelineStage =
+    | 'queued'
+    | 'analyzing_food'
+    | 'generating_image'
+    | 'generating_mesh'
+    | 'rigging'
+    | 'animating'
+    | 'downloading'
+    | 'saving';
+
+export type PipelineEvent =
+    | {
+        type: 'progress';
+        job_id: string;
+        stage: PipelineStage;
+        detail: string | null;
+        stage_percent: number;
+        overall_percent: number;
+        elapsed_ms: number;
+        eta_ms: number | null;
+    }
+    | { type: 'rigging_fallback'; job_id: string; reason: string; overall_percent: number; elapsed_ms: number }
+    | { type: 'completed'; job_id: string; robot_id: string; elapsed_ms: number }
+    | { type: 'failed'; job_id: string; error: AppError; elapsed_ms: number }
+    | { type: 'cancelled'; job_id: string; elapsed_ms: number };
+
+export interface MigrationError {
+    from_version: number;
+    failed_version: number;
+    message: string;
+}
+
+export interface DbStatus {
+    schema_version: number;
+    target_version: number;
+    migration_error: MigrationError | null;
+}
+
+export type KeyedProvider = 'gemini' | 'meshy';
+
+export interface ApiKeyStatus {
+    provider: KeyedProvider;
+    source: 'env' | 'stored' | 'missing';
+    hint: string | null;
+}
+
+export interface ConnectionCheck {
+    provider: KeyedProvider;
+    ok: boolean;
+    latency_ms: number;
+    error: AppError | null;
+}
+
+export interface StatBounds {
+    min: number;
+    max: number;
+}
+
+export interface NutrientRange {
+    min: number;
+    max: number;
+}
+
+export interface Settings {
+    version: number;
+    gemini: {
+        stats_model: string;
+        image_model: string;
+    };
+    meshy: {
+        enable_pbr: boolean;
+        idle_action_id: number;
+        attack_action_id: number;
+        polling: {
+            initial_delay_ms: number;
+            max_delay_ms: number;
+            backoff_multiplier: number;
+            mesh_timeout_secs: number;
+            rigging_timeout_secs: number;
+            animation_timeout_secs: number;
+        };
+    };
+    stats: {
+        hp: StatBounds;
+        atk: StatBounds;
+        def: StatBounds;
+        out_of_range: 'clamp' | 'reject';
+        formula: {
+            calories: NutrientRange;
+            protein: NutrientRange;
+            fiber: NutrientRange;
+        };
+    };
+}
+
+export type OrphanAction = 'report' | 'quarantine' | 'delete';
+
+export interface MissingAsset {
+    robot_id: string;
+    robot_name: string;
+    field: 'original_image_path' | 'image_path' | 'model_path' | 'attack_model_path';
+    asset_id: string;
+}
+
+export interface MaintenanceReport {
+    missing: MissingAsset[];
+    /** Paths relative to the app data dir. */
+    orphans: string[];
+    action: OrphanAction;
+    usage: {
+        asset_bytes: number;
+        orphan_bytes: number;
+        quarantine_bytes: number;
+        database_bytes: number;
+        total_bytes: number;
+    };
+}
+
+export type ConflictPolicy = 'skip' | 'overwrite' | 'duplicate';
+
+export interface ImportResult {
+    /** 'conflict': the id already exists and nothing was imported; `robot` is the existing one. */
+    status: 'imported' | 'skipped' | 'conflict';
+    robot: RobotRecord;
 }